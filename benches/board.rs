@@ -0,0 +1,99 @@
+//! Compares the `Board` collision and line clear paths with the way the game did it when
+//! the playfield lived in `Tile` entities: scanning every tile for every cell of the piece,
+//! and rebuilding a matrix from the tiles to clear lines.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tetris_rs::engine::{Board, Tetramino, TetraminoType, COLS, ROWS};
+
+/// Stand-in for the old `Tile` component, one per cell of the playfield.
+struct Tile {
+    x: i32,
+    y: i32,
+    value: u8,
+}
+
+fn tiles_from(board: &Board) -> Vec<Tile> {
+    let mut tiles = Vec::with_capacity(COLS * ROWS);
+    for x in 0..COLS as i32 {
+        for y in 0..ROWS as i32 {
+            tiles.push(Tile { x, y, value: board.get(x, y) });
+        }
+    }
+    tiles
+}
+
+/// The old `check_is_collided` for a plain move.
+fn old_is_collided(tiles: &[Tile], tetramino: &Tetramino) -> bool {
+    tetramino.cells().any(|(x, y)| {
+        x < 0 || x >= COLS as i32 || y < 0
+            || tiles.iter().any(|tile| tile.x == x && tile.y == y && tile.value > 0)
+    })
+}
+
+fn new_is_collided(board: &Board, tetramino: &Tetramino) -> bool {
+    tetramino.cells().any(|(x, y)| board.is_blocked(x, y))
+}
+
+/// The old `burn_the_line`, clearing one full line per pass over the rebuilt matrix.
+fn old_clear_full_lines(tiles: &mut [Tile]) -> usize {
+    let mut matrix = vec![vec![0; COLS]; ROWS + 1];
+    for tile in tiles.iter() {
+        matrix[tile.y as usize][tile.x as usize] = tile.value;
+    }
+    let mut count = 0;
+    while let Some(line) = (0..ROWS).find(|&y| matrix[y].iter().all(|&value| value != 0)) {
+        count += 1;
+        for y in line..ROWS {
+            let above = matrix[y + 1].clone();
+            matrix[y] = above;
+        }
+    }
+    if count > 0 {
+        for tile in tiles.iter_mut() {
+            tile.value = matrix[tile.y as usize][tile.x as usize];
+        }
+    }
+    count
+}
+
+/// A messy stack eight rows high with four full rows in it.
+fn stack() -> Board {
+    let mut board = Board::new();
+    for y in 0..8 {
+        for x in 0..COLS as i32 {
+            if y % 2 == 0 || (x + y) % 3 != 0 {
+                board.set(x, y, 1 + (x as u8 + y as u8) % 7);
+            }
+        }
+    }
+    board
+}
+
+fn collision(c: &mut Criterion) {
+    let board = stack();
+    let tiles = tiles_from(&board);
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::T);
+    (tetramino.x, tetramino.y) = (3, 8);
+
+    let mut group = c.benchmark_group("collision");
+    group.bench_function("tiles", |b| b.iter(|| old_is_collided(black_box(&tiles), black_box(&tetramino))));
+    group.bench_function("board", |b| b.iter(|| new_is_collided(black_box(&board), black_box(&tetramino))));
+    group.finish();
+}
+
+fn line_clear(c: &mut Criterion) {
+    let board = stack();
+
+    let mut group = c.benchmark_group("line clear");
+    group.bench_function("tiles", |b| {
+        b.iter_batched_ref(|| tiles_from(&board), |tiles| old_clear_full_lines(tiles), criterion::BatchSize::SmallInput)
+    });
+    group.bench_function("board", |b| {
+        b.iter_batched_ref(|| board.clone(), |board| board.clear_full_lines(), criterion::BatchSize::SmallInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, collision, line_clear);
+criterion_main!(benches);
@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+use crate::settings::{Action, Settings};
+use crate::state::{check_state_change, despawn_with, screen_text, GameState};
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Rebinding::default())
+            .add_system_set(SystemSet::on_enter(GameState::Controls).with_system(create_controls_screen))
+            .add_system_set(SystemSet::on_update(GameState::Controls)
+                .with_system(controls_input)
+                .with_system(update_controls_text.after(controls_input)))
+            .add_system_set(SystemSet::on_exit(GameState::Controls)
+                .with_system(despawn_with::<ControlsScreen>)
+                .with_system(save_settings));
+    }
+}
+
+#[derive(Component)]
+struct ControlsScreen;
+
+/// Cursor of the controls screen. While `listening` the next key pressed is bound to the
+/// selected action.
+#[derive(Default)]
+struct Rebinding {
+    selected: usize,
+    listening: bool,
+}
+
+fn create_controls_screen(mut commands: Commands, asset_server: Res<AssetServer>, mut rebinding: ResMut<Rebinding>) {
+    *rebinding = Rebinding::default();
+    let mut text = screen_text(&asset_server, String::new());
+    text.text.sections[0].style.font_size = 24.0;
+    commands.spawn_bundle(text).insert(ControlsScreen);
+}
+
+fn controls_input(
+    mut keys: ResMut<Input<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let action = Action::ALL[rebinding.selected];
+    if rebinding.listening {
+        let key = keys.get_just_pressed().next().copied();
+        if let Some(key) = key {
+            if key != KeyCode::Escape {
+                settings.bindings.bind(action, key);
+            }
+            keys.reset(key);
+            rebinding.listening = false;
+        }
+    } else if keys.just_pressed(KeyCode::Up) {
+        rebinding.selected = (rebinding.selected + Action::ALL.len() - 1) % Action::ALL.len();
+    } else if keys.just_pressed(KeyCode::Down) {
+        rebinding.selected = (rebinding.selected + 1) % Action::ALL.len();
+    } else if keys.just_pressed(KeyCode::Return) {
+        keys.reset(KeyCode::Return);
+        rebinding.listening = true;
+    } else if keys.any_just_pressed([KeyCode::Back, KeyCode::Delete]) {
+        settings.bindings.clear(action);
+    } else if keys.just_pressed(KeyCode::Escape) {
+        keys.reset(KeyCode::Escape);
+        check_state_change(state.set(GameState::Menu));
+    }
+}
+
+fn update_controls_text(
+    rebinding: Res<Rebinding>,
+    settings: Res<Settings>,
+    mut query: Query<&mut Text, With<ControlsScreen>>,
+) {
+    if !rebinding.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let mut value = "Controls\n".to_string();
+    for (index, action) in Action::ALL.into_iter().enumerate() {
+        let cursor = if index == rebinding.selected { "> " } else { "  " };
+        let keys: Vec<String> = settings.bindings.keys(action).iter().map(|key| format!("{:?}", key)).collect();
+        value += &format!("{}{}: {}\n", cursor, action, keys.join(", "));
+    }
+    if rebinding.listening {
+        value += &format!("\nPress a key for {}, Esc to cancel", Action::ALL[rebinding.selected]);
+    } else {
+        value += "\nUp/Down to select, Enter to add a key\nBackspace to clear, Esc to go back";
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    settings.save();
+}
@@ -0,0 +1,301 @@
+/// Size of the visible part of the standard playfield, used unless `GameConfig` asks for
+/// another one.
+pub const ROWS: usize = 20;
+pub const COLS: usize = 10;
+
+/// Rows hidden above the visible part of every board. Pieces spawn at the bottom of them
+/// and the stack can grow into them.
+pub const BUFFER_ROWS: usize = 20;
+
+/// Bounds for the width of a board. Every row is a `u32` bitmask, and anything narrower
+/// than an I piece could not spawn one.
+pub const MIN_COLS: usize = 4;
+pub const MAX_COLS: usize = 32;
+/// Smallest visible height a board can have.
+pub const MIN_ROWS: usize = 4;
+
+/// Locked cells of the playfield, stored row after row from the bottom, `y = 0`, up.
+/// A value of zero is an empty cell, anything else is `TetraminoType::value` of the piece
+/// that was locked there.
+///
+/// Every row also keeps a bitmask of its filled cells, bit `x` for column `x`, so that
+/// collision and full row checks never look at the values.
+///
+/// The board is `BUFFER_ROWS` taller than the part the player sees, everything from
+/// `visible_height` up is the hidden buffer.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Board {
+    width: usize,
+    height: usize,
+    visible_height: usize,
+    cells: Vec<u8>,
+    rows: Vec<u32>,
+}
+
+impl Board {
+    /// An empty standard `COLS` by `ROWS` board.
+    pub fn new() -> Self {
+        Self::with_size(COLS, ROWS)
+    }
+
+    /// An empty board `width` wide with `visible_height` rows below the buffer, `width`
+    /// clamped to `MIN_COLS..=MAX_COLS` and `visible_height` to at least `MIN_ROWS`.
+    pub fn with_size(width: usize, visible_height: usize) -> Self {
+        let width = width.clamp(MIN_COLS, MAX_COLS);
+        let visible_height = visible_height.max(MIN_ROWS);
+        let height = visible_height + BUFFER_ROWS;
+        Self {
+            width,
+            height,
+            visible_height,
+            cells: vec![0; width * height],
+            rows: vec![0; height],
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width as i32
+    }
+
+    /// Rows of the whole board, the hidden buffer included.
+    pub fn height(&self) -> i32 {
+        self.height as i32
+    }
+
+    /// Rows below the hidden buffer.
+    pub fn visible_height(&self) -> i32 {
+        self.visible_height as i32
+    }
+
+    /// Whether row `y` is in the hidden buffer.
+    pub fn is_hidden(&self, y: i32) -> bool {
+        y >= self.visible_height()
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x < self.width() && y >= 0 && y < self.height()
+    }
+
+    fn index(&self, x: i32, y: i32) -> usize {
+        y as usize * self.width + x as usize
+    }
+
+    /// Mask of a row with every column filled.
+    fn full_row(&self) -> u32 {
+        ((1u64 << self.width) - 1) as u32
+    }
+
+    /// Where the 4x4 box of a new piece goes: centred, leaning left on odd widths, with
+    /// the piece in the two rows right above the visible part.
+    pub fn spawn_position(&self) -> (i32, i32) {
+        (self.width() / 2 - 2, self.visible_height() - 2)
+    }
+
+    /// Returns the value of the cell, or zero for coordinates outside of the board.
+    pub fn get(&self, x: i32, y: i32) -> u8 {
+        if !self.in_bounds(x, y) {
+            return 0;
+        }
+        self.cells[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, value: u8) {
+        if !self.in_bounds(x, y) {
+            return;
+        }
+        let index = self.index(x, y);
+        self.cells[index] = value;
+        if value == 0 {
+            self.rows[y as usize] &= !(1 << x);
+        } else {
+            self.rows[y as usize] |= 1 << x;
+        }
+    }
+
+    /// A cell is blocked if it is filled or lies outside of the walls and the floor.
+    pub fn is_blocked(&self, x: i32, y: i32) -> bool {
+        !self.in_bounds(x, y) || self.rows[y as usize] & (1 << x) != 0
+    }
+
+    pub fn is_line_full(&self, y: usize) -> bool {
+        self.rows[y] == self.full_row()
+    }
+
+    pub fn is_line_empty(&self, y: usize) -> bool {
+        self.rows[y] == 0
+    }
+
+    /// Rows that are full, from the bottom up.
+    pub fn full_lines(&self) -> Vec<usize> {
+        (0..self.height).filter(|&y| self.is_line_full(y)).collect()
+    }
+
+    /// Removes every full line, shifting the lines above it down, and returns how many
+    /// lines were removed.
+    pub fn clear_full_lines(&mut self) -> usize {
+        let width = self.width;
+        let mut kept = 0;
+        for y in 0..self.height {
+            if self.is_line_full(y) {
+                continue;
+            }
+            if kept != y {
+                self.rows[kept] = self.rows[y];
+                self.cells.copy_within(y * width..(y + 1) * width, kept * width);
+            }
+            kept += 1;
+        }
+        self.rows[kept..].fill(0);
+        self.cells[kept * width..].fill(0);
+        self.height - kept
+    }
+
+    /// Values of row `y`, from the left.
+    pub fn row(&self, y: usize) -> &[u8] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    /// Pushes the whole stack up one row and puts `row` in at the bottom. Anything in the
+    /// top row is pushed off the board; returns false if there was.
+    pub fn push_bottom(&mut self, row: &[u8]) -> bool {
+        let width = self.width;
+        let overflow = !self.is_line_empty(self.height - 1);
+        self.rows.copy_within(0..self.height - 1, 1);
+        self.cells.copy_within(0..(self.height - 1) * width, width);
+        self.rows[0] = 0;
+        for x in 0..width {
+            self.set(x as i32, 0, row.get(x).copied().unwrap_or(0));
+        }
+        !overflow
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.iter().all(|&row| row == 0)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(0);
+        self.rows.fill(0);
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_is_blocked() {
+    let mut board = Board::new();
+    board.set(3, 0, 1);
+    assert!(board.is_blocked(3, 0));
+    assert!(!board.is_blocked(4, 0));
+    assert!(board.is_blocked(-1, 0));
+    assert!(board.is_blocked(COLS as i32, 0));
+    assert!(board.is_blocked(0, -1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_clear_full_lines() {
+    let mut board = Board::new();
+    for x in 0..COLS as i32 {
+        board.set(x, 0, 1);
+        board.set(x, 2, 2);
+    }
+    board.set(0, 1, 3);
+    board.set(5, 3, 4);
+    assert!(board.is_line_full(0));
+    assert_eq!(board.clear_full_lines(), 2);
+    assert_eq!(board.get(0, 0), 3);
+    assert_eq!(board.get(5, 1), 4);
+    assert_eq!(board.get(5, 2), 0);
+    assert!(!board.is_line_full(0));
+    assert!(board.is_blocked(5, 1));
+    assert!(!board.is_blocked(5, 3));
+}
+
+#[cfg(test)]
+#[test]
+fn test_emptying_a_cell_unblocks_it() {
+    let mut board = Board::new();
+    for x in 0..COLS as i32 {
+        board.set(x, 4, 1);
+    }
+    assert!(board.is_line_full(4));
+    board.set(2, 4, 0);
+    assert!(!board.is_line_full(4));
+    assert!(!board.is_blocked(2, 4));
+    assert!(!board.is_empty());
+    board.clear();
+    assert!(board.is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_board_size() {
+    let board = Board::with_size(16, 30);
+    assert_eq!((board.width(), board.visible_height(), board.height()), (16, 30, 50));
+    assert!(!board.is_blocked(15, 49));
+    assert!(board.is_blocked(15, 50));
+    assert!(board.is_blocked(16, 0));
+    assert!(!board.is_hidden(29));
+    assert!(board.is_hidden(30));
+    assert_eq!(board.spawn_position(), (6, 28));
+    assert_eq!(Board::new().spawn_position(), (3, 18));
+    assert_eq!(Board::with_size(5, 20).spawn_position(), (0, 18));
+    let clamped = Board::with_size(40, 1);
+    assert_eq!((clamped.width(), clamped.visible_height()), (MAX_COLS as i32, MIN_ROWS as i32));
+}
+
+#[cfg(test)]
+#[test]
+fn test_full_lines_on_the_widest_board() {
+    let mut board = Board::with_size(MAX_COLS, 8);
+    for x in 0..MAX_COLS as i32 {
+        board.set(x, 1, 1);
+    }
+    board.set(0, 2, 2);
+    assert_eq!(board.full_lines(), vec![1]);
+    assert_eq!(board.clear_full_lines(), 1);
+    assert_eq!(board.get(0, 1), 2);
+    assert!(board.is_line_empty(2));
+}
+
+#[cfg(test)]
+#[test]
+fn test_push_bottom_lifts_the_stack() {
+    let mut board = Board::new();
+    board.set(2, 0, 3);
+    let row = [8, 8, 0, 8, 8, 8, 8, 8, 8, 8];
+    assert!(board.push_bottom(&row));
+    assert_eq!(board.row(0), &row);
+    assert!(!board.is_blocked(2, 0));
+    assert_eq!(board.get(2, 1), 3);
+    board.set(0, board.height() - 1, 1);
+    assert!(!board.push_bottom(&row));
+    assert!(board.is_line_empty(board.height() as usize - 1));
+    assert_eq!(board.get(2, 2), 3);
+}
+
+#[cfg(test)]
+#[test]
+fn test_full_lines_on_a_20_wide_board() {
+    let mut board = Board::with_size(20, 20);
+    assert_eq!(board.width(), 20);
+    assert_eq!(board.spawn_position(), (8, 18));
+    for x in 0..19 {
+        board.set(x, 0, 1);
+        board.set(x, 1, 1);
+    }
+    assert!(board.full_lines().is_empty());
+    board.set(19, 1, 2);
+    assert!(board.is_blocked(19, 1));
+    assert!(board.is_blocked(20, 1));
+    assert_eq!(board.full_lines(), vec![1]);
+    assert_eq!(board.clear_full_lines(), 1);
+    assert!(!board.is_line_full(0));
+    assert!(board.is_line_empty(1));
+}
@@ -0,0 +1,88 @@
+//! Garbage rows for the dig mode.
+
+use std::fmt;
+use std::str::FromStr;
+use rand::seq::index;
+use rand::{Rng, RngCore};
+
+/// Value stored in the board for a garbage cell, after the values of the seven pieces.
+pub const GARBAGE: u8 = 8;
+
+/// Most holes a messy garbage row gets.
+pub const MAX_MESSY_HOLES: usize = 3;
+
+/// How the holes of garbage rows are laid out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GarbageStyle {
+    /// One hole per row, never in the same column as the row below.
+    #[default]
+    Clean,
+    /// One to `MAX_MESSY_HOLES` holes per row anywhere.
+    Messy,
+}
+
+impl GarbageStyle {
+    /// A garbage row `width` cells wide, `GARBAGE` everywhere but in its holes.
+    /// `previous_hole` is a hole of the row it goes under, if there is one.
+    pub fn row(self, width: usize, previous_hole: Option<usize>, rng: &mut dyn RngCore) -> Vec<u8> {
+        let mut row = vec![GARBAGE; width];
+        match self {
+            GarbageStyle::Clean => {
+                let hole = match previous_hole {
+                    Some(previous) if width > 1 => (previous + rng.gen_range(1..width)) % width,
+                    _ => rng.gen_range(0..width),
+                };
+                row[hole] = 0;
+            }
+            GarbageStyle::Messy => {
+                let holes = rng.gen_range(1..=MAX_MESSY_HOLES.min(width - 1));
+                for hole in index::sample(rng, width, holes) {
+                    row[hole] = 0;
+                }
+            }
+        }
+        row
+    }
+}
+
+impl FromStr for GarbageStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clean" => Ok(GarbageStyle::Clean),
+            "messy" => Ok(GarbageStyle::Messy),
+            _ => Err(format!("unknown garbage style `{}`, expected clean or messy", s)),
+        }
+    }
+}
+
+impl fmt::Display for GarbageStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            GarbageStyle::Clean => "clean",
+            GarbageStyle::Messy => "messy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_garbage_rows_have_holes() {
+    use rand::SeedableRng;
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(7);
+    let holes = |row: &[u8]| (0..row.len()).filter(|&x| row[x] == 0).collect::<Vec<_>>();
+    let mut previous = None;
+    for _ in 0..50 {
+        let row = GarbageStyle::Clean.row(10, previous, &mut rng);
+        let hole = holes(&row);
+        assert_eq!(hole.len(), 1);
+        assert_ne!(Some(hole[0]), previous);
+        previous = Some(hole[0]);
+        let messy = holes(&GarbageStyle::Messy.row(10, previous, &mut rng)).len();
+        assert!((1..=MAX_MESSY_HOLES).contains(&messy));
+    }
+    assert_eq!("messy".parse::<GarbageStyle>(), Ok(GarbageStyle::Messy));
+    assert_eq!(GarbageStyle::Clean.to_string(), "clean");
+}
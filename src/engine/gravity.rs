@@ -0,0 +1,98 @@
+//! How fast pieces fall at each level.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The NES runs at about 60.0988 frames per second.
+const NES_FRAME: f32 = 1.0 / 60.0988;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GravityCurve {
+    /// `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per line, the formula from the
+    /// Tetris guideline. Passes 20G a little before level 20.
+    #[default]
+    Guideline,
+    /// Frames per line from NES Tetris, where our level 1 is NES level 0.
+    Nes,
+}
+
+impl GravityCurve {
+    /// Seconds it takes a piece to fall one line at `level`, starting from level 1. Below
+    /// a frame this is sub-frame gravity and pieces fall several lines per step.
+    pub fn fall_interval(&self, level: u32) -> f32 {
+        let level = level.max(1);
+        match self {
+            GravityCurve::Guideline => {
+                let n = (level - 1) as f32;
+                (0.8 - n * 0.007).powf(n)
+            }
+            GravityCurve::Nes => {
+                let frames = match level - 1 {
+                    0 => 48,
+                    1 => 43,
+                    2 => 38,
+                    3 => 33,
+                    4 => 28,
+                    5 => 23,
+                    6 => 18,
+                    7 => 13,
+                    8 => 8,
+                    9 => 6,
+                    10..=12 => 5,
+                    13..=15 => 4,
+                    16..=18 => 3,
+                    19..=28 => 2,
+                    _ => 1,
+                };
+                frames as f32 * NES_FRAME
+            }
+        }
+    }
+}
+
+impl FromStr for GravityCurve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "guideline" => Ok(GravityCurve::Guideline),
+            "nes" => Ok(GravityCurve::Nes),
+            _ => Err(format!("unknown gravity curve `{}`, expected guideline or nes", s)),
+        }
+    }
+}
+
+impl fmt::Display for GravityCurve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            GravityCurve::Guideline => "guideline",
+            GravityCurve::Nes => "nes",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_guideline_fall_interval() {
+    let curve = GravityCurve::Guideline;
+    assert_eq!(curve.fall_interval(1), 1.0);
+    assert!((curve.fall_interval(2) - 0.793).abs() < 1e-6);
+    assert!((curve.fall_interval(10) - 0.0642).abs() < 1e-4);
+    // 20G, more than the whole board every 60th of a second.
+    assert!(curve.fall_interval(20) < 1.0 / 60.0 / 20.0);
+    for level in 1..30 {
+        assert!(curve.fall_interval(level + 1) < curve.fall_interval(level));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_nes_fall_interval() {
+    let curve = GravityCurve::Nes;
+    assert!((curve.fall_interval(1) - 48.0 * NES_FRAME).abs() < 1e-6);
+    assert!((curve.fall_interval(20) - 2.0 * NES_FRAME).abs() < 1e-6);
+    assert_eq!(curve.fall_interval(30), NES_FRAME);
+    assert_eq!(curve.fall_interval(100), NES_FRAME);
+    assert_eq!("nes".parse::<GravityCurve>(), Ok(curve));
+}
@@ -0,0 +1,54 @@
+//! When a piece resting on the stack gets locked in place.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Moves and rotations that can restart the lock delay of one piece under
+/// `LockReset::Extended`. The count starts over when the piece reaches a new lowest row.
+pub const MAX_LOCK_RESETS: u32 = 15;
+
+/// What restarts the lock delay once a piece touches the stack. Falling a line always does.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LockReset {
+    /// Every successful move or rotation on the stack restarts the delay.
+    Infinite,
+    /// Like `Infinite`, but only `MAX_LOCK_RESETS` times per row.
+    #[default]
+    Extended,
+    /// Only falling restarts the delay, moving and rotating do not.
+    Classic,
+}
+
+impl FromStr for LockReset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "infinite" => Ok(LockReset::Infinite),
+            "extended" => Ok(LockReset::Extended),
+            "classic" | "step" => Ok(LockReset::Classic),
+            _ => Err(format!("unknown lock reset `{}`, expected infinite, extended or classic", s)),
+        }
+    }
+}
+
+impl fmt::Display for LockReset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LockReset::Infinite => "infinite",
+            LockReset::Extended => "extended",
+            LockReset::Classic => "classic",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_lock_reset_from_str() {
+    for reset in [LockReset::Infinite, LockReset::Extended, LockReset::Classic] {
+        assert_eq!(reset.to_string().parse::<LockReset>(), Ok(reset));
+    }
+    assert_eq!("step".parse::<LockReset>(), Ok(LockReset::Classic));
+    assert!("none".parse::<LockReset>().is_err());
+}
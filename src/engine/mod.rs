@@ -0,0 +1,1383 @@
+//! Rules of the game without any dependency on Bevy. The Bevy plugins only feed
+//! `PlayerInput` into `Game::step` and draw whatever the `Game` looks like afterwards.
+
+pub mod board;
+pub mod garbage;
+pub mod gravity;
+pub mod lock;
+pub mod mode;
+pub mod piece;
+pub mod randomizer;
+pub mod scoring;
+pub mod srs;
+pub mod tspin;
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+pub use board::{Board, BUFFER_ROWS, COLS, MAX_COLS, MIN_COLS, MIN_ROWS, ROWS};
+pub use garbage::{GarbageStyle, GARBAGE};
+pub use gravity::GravityCurve;
+pub use lock::{LockReset, MAX_LOCK_RESETS};
+pub use mode::{GameMode, LineGoal, DIG_HEIGHT, DIG_LINES, MARATHON_LINES, MARATHON_MAX_LEVEL, SPRINT_LINES, ULTRA_SECONDS};
+pub use piece::{Rotation, RotationDirection, Tetramino, TetraminoType};
+pub use randomizer::{Randomizer, RandomizerKind};
+pub use scoring::{LineClear, ScoreEvent, ScoreState};
+pub use tspin::TSpin;
+
+/// Bounds for `GameConfig::preview_count`.
+pub const MIN_PREVIEW: usize = 1;
+pub const MAX_PREVIEW: usize = 6;
+
+/// Lines needed to go up a level.
+pub const LINES_PER_LEVEL: u32 = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TetrisData {
+    pub score: i32,
+    pub level: u32,
+    pub lines: u32,
+    /// Pieces locked so far.
+    pub pieces: u32,
+    /// Buttons pressed so far, holding one down counts once.
+    pub keys: u32,
+    /// Clears of one, two, three and four lines so far, T-spins included.
+    pub singles: u32,
+    pub doubles: u32,
+    pub triples: u32,
+    pub tetrises: u32,
+    /// T-spins so far, minis and the ones that clear nothing included.
+    pub t_spins: u32,
+    pub perfect_clears: u32,
+    /// Lines with garbage in them cleared so far.
+    pub garbage_lines: u32,
+    /// Time played, counted from the first step with a button down.
+    pub time: Duration,
+}
+
+impl TetrisData {
+    /// Zero until the timer starts.
+    pub fn pieces_per_second(&self) -> f32 {
+        let seconds = self.time.as_secs_f32();
+        if seconds > 0.0 {
+            self.pieces as f32 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Zero until a piece locks.
+    pub fn keys_per_piece(&self) -> f32 {
+        if self.pieces > 0 {
+            self.keys as f32 / self.pieces as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for TetrisData {
+    fn default() -> Self {
+        Self {
+            score: 0,
+            level: 1,
+            lines: 0,
+            pieces: 0,
+            keys: 0,
+            singles: 0,
+            doubles: 0,
+            triples: 0,
+            tetrises: 0,
+            t_spins: 0,
+            perfect_clears: 0,
+            garbage_lines: 0,
+            time: Duration::ZERO,
+        }
+    }
+}
+
+/// Options fixed for the whole game. `Game::restart` keeps them.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GameConfig {
+    /// Size of the visible part of the board in cells, see `Board::with_size` for the
+    /// limits. The hidden buffer comes on top of `height`.
+    pub width: usize,
+    pub height: usize,
+    pub mode: GameMode,
+    /// Level the game starts at, going up from there every `LINES_PER_LEVEL` lines. Only
+    /// modes with `GameMode::has_start_level` use it.
+    pub start_level: u32,
+    /// Lines that finish the game in modes that end on lines. A dig race counts garbage
+    /// lines only.
+    pub line_goal: LineGoal,
+    /// How the garbage of a dig race is laid out.
+    pub garbage: GarbageStyle,
+    /// Seconds the game lasts in timed modes, `None` for the default of the mode.
+    pub time_limit: Option<u32>,
+    pub randomizer: RandomizerKind,
+    /// Seed for every random choice of the game. Two games with the same seed and the same
+    /// randomizer deal the same pieces; `None` picks a new seed on every start.
+    pub seed: Option<u64>,
+    /// How many upcoming pieces `Game::queue` holds, clamped to `MIN_PREVIEW..=MAX_PREVIEW`.
+    pub preview_count: usize,
+    pub gravity: GravityCurve,
+    /// Seconds a piece can rest on the stack before it locks.
+    pub lock_delay: f32,
+    pub lock_reset: LockReset,
+    /// Delayed auto shift: seconds left or right has to be held before the piece starts
+    /// sliding on its own.
+    pub das: f32,
+    /// Auto repeat rate: seconds between two moves once DAS is charged. Zero moves the
+    /// piece straight to the wall.
+    pub arr: f32,
+    /// Seconds auto shift holds off after a piece spawns or rotates, so a charged DAS does
+    /// not throw it against the wall right away. DAS keeps charging meanwhile.
+    pub das_cut: f32,
+    /// Gravity is this many times faster while soft drop is held. Infinity drops the piece
+    /// to the stack without locking it.
+    pub soft_drop_factor: f32,
+    /// Seconds full rows stay on the board before the stack collapses.
+    pub line_clear_delay: f32,
+    /// ARE, the seconds between a piece locking, or the stack collapsing, and the next
+    /// piece spawning.
+    pub spawn_delay: f32,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            width: COLS,
+            height: ROWS,
+            mode: GameMode::default(),
+            start_level: 1,
+            line_goal: LineGoal::Default,
+            garbage: GarbageStyle::default(),
+            time_limit: None,
+            randomizer: RandomizerKind::default(),
+            seed: None,
+            preview_count: 5,
+            gravity: GravityCurve::default(),
+            lock_delay: 0.5,
+            lock_reset: LockReset::default(),
+            das: 0.167,
+            arr: 0.033,
+            das_cut: 0.0,
+            soft_drop_factor: 20.0,
+            line_clear_delay: 0.3,
+            spawn_delay: 0.1,
+        }
+    }
+}
+
+/// Why the game ended.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameOverReason {
+    /// The next piece could not spawn because the stack overlaps its spawn position.
+    BlockOut,
+    /// A piece locked with every cell in the hidden buffer.
+    LockOut,
+    /// Garbage coming in pushed the stack off the top of the board.
+    TopOut,
+    /// The goal of the mode was reached, or its time ran out. Unlike the other reasons
+    /// this is a win.
+    Finished,
+}
+
+/// What the game is waiting for. `Game::active` is only in play while `Falling`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Phase {
+    Falling,
+    /// Full `rows` stay on the board for `GameConfig::line_clear_delay`, `timer` counting
+    /// the seconds spent so far.
+    LineClear { rows: Vec<usize>, timer: f32 },
+    /// Waiting `GameConfig::spawn_delay` for the next piece.
+    Spawning { timer: f32 },
+}
+
+/// Buttons held down during a step. `Game` remembers the previous input, so an action
+/// only fires on the step its button goes down. Soft drop works for as long as it is
+/// held, and left and right repeat once held longer than `GameConfig::das`.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlayerInput {
+    pub left: bool,
+    pub right: bool,
+    pub rotate_clockwise: bool,
+    pub rotate_conterclockwise: bool,
+    pub rotate_180: bool,
+    pub hold: bool,
+    pub soft_drop: bool,
+    pub hard_drop: bool,
+}
+
+impl PlayerInput {
+    /// How many buttons went down since `previous`.
+    fn presses(&self, previous: &PlayerInput) -> u32 {
+        [
+            (self.left, previous.left),
+            (self.right, previous.right),
+            (self.rotate_clockwise, previous.rotate_clockwise),
+            (self.rotate_conterclockwise, previous.rotate_conterclockwise),
+            (self.rotate_180, previous.rotate_180),
+            (self.hold, previous.hold),
+            (self.soft_drop, previous.soft_drop),
+            (self.hard_drop, previous.hard_drop),
+        ]
+        .into_iter()
+        .filter(|&(now, before)| now && !before)
+        .count() as u32
+    }
+
+    fn just_pressed(&self, previous: &PlayerInput) -> PlayerInput {
+        PlayerInput {
+            left: self.left && !previous.left,
+            right: self.right && !previous.right,
+            rotate_clockwise: self.rotate_clockwise && !previous.rotate_clockwise,
+            rotate_conterclockwise: self.rotate_conterclockwise && !previous.rotate_conterclockwise,
+            rotate_180: self.rotate_180 && !previous.rotate_180,
+            hold: self.hold && !previous.hold,
+            soft_drop: self.soft_drop,
+            hard_drop: self.hard_drop && !previous.hard_drop,
+        }
+    }
+}
+
+pub struct Game {
+    pub config: GameConfig,
+    pub board: Board,
+    pub active: Tetramino,
+    /// Upcoming pieces, the front one spawns next.
+    pub queue: VecDeque<TetraminoType>,
+    pub hold: Option<TetraminoType>,
+    /// Holding is allowed once per piece, this turns false on hold and back on lock.
+    pub can_hold: bool,
+    pub data: TetrisData,
+    pub phase: Phase,
+    pub scoring: ScoreState,
+    /// What the last piece to lock did, for the UI.
+    pub last_clear: Option<LineClear>,
+    pub game_over: Option<GameOverReason>,
+    /// The seed this game was started with, whether configured or picked at random.
+    pub seed: u64,
+    rng: ChaCha8Rng,
+    /// Holes of the garbage rows come from their own stream, so that a seed deals the same
+    /// pieces in every mode.
+    garbage_rng: ChaCha8Rng,
+    /// Garbage rows put on the board so far.
+    garbage_added: u32,
+    randomizer: Box<dyn Randomizer>,
+    fall_timer: f32,
+    /// Seconds the active piece has spent on the stack since the lock delay last restarted.
+    lock_timer: f32,
+    lock_resets: u32,
+    /// Lowest row the active piece reached, `lock_resets` starts over below it.
+    lowest_y: i32,
+    previous_input: PlayerInput,
+    /// Direction and SRS kick index of the last rotation, `None` once the piece moved since.
+    last_kick: Option<(RotationDirection, usize)>,
+    score_events: Vec<ScoreEvent>,
+    /// -1 while left is shifting the piece, 1 for right, 0 for neither.
+    shift_direction: i32,
+    das_timer: f32,
+    arr_timer: f32,
+    das_cut_timer: f32,
+    /// Whether `TetrisData::time` runs, which it does from the first button press on.
+    timer_started: bool,
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::with_config(GameConfig::default())
+    }
+
+    pub fn with_config(config: GameConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let mut garbage_rng = ChaCha8Rng::seed_from_u64(seed);
+        garbage_rng.set_stream(1);
+        let mut game = Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            garbage_rng,
+            garbage_added: 0,
+            randomizer: config.randomizer.create(),
+            board: Board::with_size(config.width, config.height),
+            config,
+            active: Tetramino::new(),
+            queue: VecDeque::new(),
+            hold: None,
+            can_hold: true,
+            data: TetrisData::default(),
+            phase: Phase::Falling,
+            scoring: ScoreState::default(),
+            last_clear: None,
+            game_over: None,
+            fall_timer: 0.0,
+            lock_timer: 0.0,
+            lock_resets: 0,
+            lowest_y: 0,
+            previous_input: PlayerInput::default(),
+            last_kick: None,
+            score_events: Vec::new(),
+            shift_direction: 0,
+            das_timer: 0.0,
+            arr_timer: 0.0,
+            das_cut_timer: 0.0,
+            timer_started: false,
+        };
+        game.data.level = game.level_for(0);
+        game.add_garbage();
+        game.spawn();
+        game
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.game_over.is_some()
+    }
+
+    /// Lines that finish this game, `None` if lines do not end it.
+    pub fn line_goal(&self) -> Option<u32> {
+        let goals = self.config.mode.line_goals();
+        match self.config.line_goal {
+            _ if goals.is_empty() => None,
+            LineGoal::Lines(lines) => Some(lines),
+            LineGoal::Unlimited if goals.contains(&LineGoal::Unlimited) => None,
+            LineGoal::Default | LineGoal::Unlimited => self.config.mode.default_line_goal(),
+        }
+    }
+
+    /// Lines counted toward `line_goal`: garbage lines in a dig race, every line otherwise.
+    pub fn goal_lines(&self) -> u32 {
+        match self.config.mode {
+            GameMode::Dig => self.data.garbage_lines,
+            _ => self.data.lines,
+        }
+    }
+
+    /// Level after clearing `lines`: the start level plus one every `LINES_PER_LEVEL`,
+    /// up to the cap of the mode.
+    fn level_for(&self, lines: u32) -> u32 {
+        let start_level = if self.config.mode.has_start_level() { self.config.start_level.max(1) } else { 1 };
+        let level = start_level + lines / LINES_PER_LEVEL;
+        self.config.mode.level_cap().map_or(level, |cap| level.min(cap))
+    }
+
+    /// How long this game lasts, `None` if it is not timed.
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.config.mode.default_time_limit()
+            .map(|seconds| Duration::from_secs(self.config.time_limit.unwrap_or(seconds).into()))
+    }
+
+    /// Time left before a timed game ends.
+    pub fn time_left(&self) -> Option<Duration> {
+        self.time_limit().map(|limit| limit.saturating_sub(self.data.time))
+    }
+
+    /// Clears the board and the score and starts over with a fresh piece.
+    pub fn restart(&mut self) {
+        *self = Self::with_config(self.config.clone());
+    }
+
+    /// Advances the game by `dt` seconds with `input` held down. Does nothing once the
+    /// game is over.
+    pub fn step(&mut self, input: &PlayerInput, dt: f32) {
+        self.score_events.clear();
+        if self.is_over() {
+            return;
+        }
+        if self.timer_started {
+            self.data.time += Duration::from_secs_f32(dt);
+            if let Some(limit) = self.time_limit() {
+                if self.data.time >= limit {
+                    self.data.time = limit;
+                    self.game_over = Some(GameOverReason::Finished);
+                    return;
+                }
+            }
+        } else if *input != PlayerInput::default() {
+            self.timer_started = true;
+        }
+        self.data.keys += input.presses(&self.previous_input);
+        let pressed = input.just_pressed(&self.previous_input);
+        self.previous_input = *input;
+
+        if self.phase != Phase::Falling {
+            // DAS keeps charging between pieces.
+            self.shift(input, &pressed, dt, false);
+            self.wait(dt);
+            return;
+        }
+
+        if pressed.hold {
+            self.try_hold();
+            if self.is_over() {
+                return;
+            }
+        }
+        let grounded = self.is_grounded();
+        let mut moved = false;
+        let rotation = if pressed.rotate_conterclockwise {
+            Some(RotationDirection::Conterclockwise)
+        } else if pressed.rotate_clockwise {
+            Some(RotationDirection::Clockwise)
+        } else if pressed.rotate_180 {
+            Some(RotationDirection::Half)
+        } else {
+            None
+        };
+        if let Some(direction) = rotation {
+            if self.try_rotate(direction) {
+                moved = true;
+                self.das_cut_timer = self.config.das_cut;
+            }
+        }
+        moved |= self.shift(input, &pressed, dt, true);
+        if moved && (grounded || self.is_grounded()) {
+            self.reset_lock_delay();
+        }
+        if pressed.hard_drop {
+            self.hard_drop();
+            if self.phase != Phase::Falling {
+                return;
+            }
+        }
+
+        let mut interval = self.fall_interval();
+        if input.soft_drop {
+            interval /= self.config.soft_drop_factor;
+        }
+        self.fall_timer += dt;
+        let lines = if interval > 0.0 {
+            let lines = (self.fall_timer / interval).floor();
+            self.fall_timer -= lines * interval;
+            lines as i32
+        } else {
+            self.fall_timer = 0.0;
+            i32::MAX
+        };
+        // With 20G and above the piece crosses the whole board in one step, there is no
+        // point in counting the lines one by one.
+        let lines = lines.min(self.board.height());
+        for _ in 0..lines {
+            if !self.fall() {
+                break;
+            }
+            if input.soft_drop {
+                self.data.score += 1;
+            }
+        }
+
+        if !self.is_over() && self.is_grounded() {
+            self.lock_timer += dt;
+            if self.lock_timer >= self.config.lock_delay {
+                self.lock();
+            }
+        }
+    }
+
+    /// Counts down the line clear and spawn delays, collapsing the stack and spawning the
+    /// next piece when they run out.
+    fn wait(&mut self, dt: f32) {
+        match &mut self.phase {
+            Phase::Falling => {}
+            Phase::LineClear { timer, .. } => {
+                *timer += dt;
+                if *timer >= self.config.line_clear_delay {
+                    self.board.clear_full_lines();
+                    self.add_garbage();
+                    if self.is_over() {
+                        return;
+                    }
+                    self.phase = Phase::Spawning { timer: 0.0 };
+                    self.wait(0.0);
+                }
+            }
+            Phase::Spawning { timer } => {
+                *timer += dt;
+                if *timer >= self.config.spawn_delay {
+                    self.phase = Phase::Falling;
+                    self.spawn();
+                }
+            }
+        }
+    }
+
+    /// Moves the active piece left or right: once on the step a direction is pressed, then
+    /// on its own after DAS. The direction pressed last wins while both are held. Without
+    /// `can_move` only the DAS charge is updated. Returns whether the piece moved.
+    fn shift(&mut self, input: &PlayerInput, pressed: &PlayerInput, dt: f32, can_move: bool) -> bool {
+        let held = |direction: i32| if direction < 0 { input.left } else { input.right };
+        let direction = if pressed.left {
+            -1
+        } else if pressed.right {
+            1
+        } else if self.shift_direction != 0 && held(self.shift_direction) {
+            self.shift_direction
+        } else if input.left {
+            -1
+        } else if input.right {
+            1
+        } else {
+            0
+        };
+
+        let pressed_now = if direction < 0 { pressed.left } else { pressed.right };
+        if direction != self.shift_direction || (direction != 0 && pressed_now) {
+            self.shift_direction = direction;
+            self.das_timer = 0.0;
+            self.arr_timer = 0.0;
+            return can_move && direction != 0 && self.try_move(direction, 0);
+        }
+        if direction == 0 {
+            return false;
+        }
+
+        let das = self.config.das;
+        let arr = self.config.arr;
+        let was_charged = self.das_timer >= das;
+        self.das_timer += dt;
+        if !can_move {
+            return false;
+        }
+        if self.das_cut_timer > 0.0 {
+            self.das_cut_timer -= dt;
+            if self.das_cut_timer > 0.0 {
+                return false;
+            }
+        }
+        if self.das_timer < das {
+            return false;
+        }
+        let mut moved = false;
+        if arr <= 0.0 {
+            while self.try_move(direction, 0) {
+                moved = true;
+            }
+            return moved;
+        }
+        // The first repeat happens the moment DAS charges, the next ones every ARR.
+        self.arr_timer += if was_charged { dt } else { self.das_timer - das + arr };
+        while self.arr_timer >= arr {
+            self.arr_timer -= arr;
+            if !self.try_move(direction, 0) {
+                self.arr_timer = 0.0;
+                break;
+            }
+            moved = true;
+        }
+        moved
+    }
+
+    /// Seconds per line of gravity at the current level.
+    pub fn fall_interval(&self) -> f32 {
+        self.config.gravity.fall_interval(self.data.level)
+    }
+
+    pub fn is_collided(&self, tetramino: &Tetramino) -> bool {
+        tetramino.cells().any(|(x, y)| self.board.is_blocked(x, y))
+    }
+
+    pub fn try_move(&mut self, dx: i32, dy: i32) -> bool {
+        let mut moved = self.active;
+        moved.x += dx;
+        moved.y += dy;
+        if self.is_collided(&moved) {
+            return false;
+        }
+        self.active = moved;
+        self.last_kick = None;
+        true
+    }
+
+    /// Rotates the active piece, trying the SRS kicks in order. The piece stays where it
+    /// was if none of them fit.
+    pub fn try_rotate(&mut self, direction: RotationDirection) -> bool {
+        let mut rotated = self.active;
+        rotated.rotate(direction);
+        let kicks = srs::kicks(rotated.tetramino_type, self.active.rotation, rotated.rotation);
+        for (index, (dx, dy)) in kicks.iter().enumerate() {
+            let mut kicked = rotated;
+            kicked.x += dx;
+            kicked.y += dy;
+            if !self.is_collided(&kicked) {
+                self.active = kicked;
+                self.last_kick = Some((direction, index));
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Puts the active piece into the hold slot and brings back the one held before, or the
+    /// next piece of the queue if the slot was empty. Either way the piece comes back in
+    /// its spawn orientation and position.
+    pub fn try_hold(&mut self) -> bool {
+        if !self.can_hold {
+            return false;
+        }
+        let held = self.hold.replace(self.active.tetramino_type);
+        match held {
+            Some(tetramino_type) => self.spawn_tetramino(tetramino_type),
+            None => self.spawn(),
+        }
+        self.can_hold = false;
+        true
+    }
+
+    /// Whether the active piece rests on the stack or the floor.
+    pub fn is_grounded(&self) -> bool {
+        let mut below = self.active;
+        below.y -= 1;
+        self.is_collided(&below)
+    }
+
+    /// Restarts the lock delay after a move or rotation on the stack, as far as
+    /// `GameConfig::lock_reset` allows.
+    fn reset_lock_delay(&mut self) {
+        self.update_lowest_y();
+        match self.config.lock_reset {
+            LockReset::Infinite => self.lock_timer = 0.0,
+            LockReset::Extended => {
+                if self.lock_resets < MAX_LOCK_RESETS {
+                    self.lock_resets += 1;
+                    self.lock_timer = 0.0;
+                }
+            }
+            LockReset::Classic => {}
+        }
+    }
+
+    fn update_lowest_y(&mut self) {
+        if self.active.y < self.lowest_y {
+            self.lowest_y = self.active.y;
+            self.lock_resets = 0;
+        }
+    }
+
+    /// How many lines the active piece can fall before it lands.
+    pub fn drop_distance(&self) -> i32 {
+        let mut dropped = self.active;
+        let mut distance = 0;
+        loop {
+            dropped.y -= 1;
+            if self.is_collided(&dropped) {
+                return distance;
+            }
+            distance += 1;
+        }
+    }
+
+    /// The active piece moved down to where a hard drop would put it.
+    pub fn ghost(&self) -> Tetramino {
+        let mut ghost = self.active;
+        ghost.y -= self.drop_distance();
+        ghost
+    }
+
+    /// Drops the active piece straight to where it lands and locks it there. Does nothing
+    /// once the game is over.
+    pub fn hard_drop(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        let distance = self.drop_distance();
+        self.active.y -= distance;
+        if distance > 0 {
+            self.last_kick = None;
+        }
+        self.data.score += 2 * distance;
+        self.lock();
+    }
+
+    /// Moves the active piece one line down, which restarts the lock delay. Returns
+    /// whether the piece moved.
+    fn fall(&mut self) -> bool {
+        if !self.try_move(0, -1) {
+            return false;
+        }
+        self.lock_timer = 0.0;
+        self.update_lowest_y();
+        true
+    }
+
+    /// Points awarded during the last step, oldest first.
+    pub fn score_events(&self) -> &[ScoreEvent] {
+        &self.score_events
+    }
+
+    /// Whether locking the active piece where it is would be a T-spin.
+    pub fn t_spin(&self) -> TSpin {
+        tspin::detect(&self.board, &self.active, self.last_kick)
+    }
+
+    fn lock(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        let t_spin = self.t_spin();
+        let value = self.active.tetramino_type.value();
+        for (x, y) in self.active.cells() {
+            self.board.set(x, y, value);
+        }
+        if self.active.cells().all(|(_, y)| self.board.is_hidden(y)) {
+            self.game_over = Some(GameOverReason::LockOut);
+            return;
+        }
+        let rows = self.board.full_lines();
+        let count = rows.len() as u32;
+        let garbage_lines = rows.iter().filter(|&&y| self.board.row(y).contains(&GARBAGE)).count() as u32;
+        let perfect_clear = count > 0
+            && (0..self.board.height() as usize).all(|y| rows.contains(&y) || self.board.is_line_empty(y));
+        let clear = LineClear {
+            lines: count,
+            t_spin,
+            perfect_clear,
+        };
+        self.last_clear = Some(clear);
+        if let Some(event) = self.scoring.award(clear, self.data.level) {
+            self.data.score += event.total;
+            self.score_events.push(event);
+        }
+        self.data.pieces += 1;
+        match count {
+            0 => {}
+            1 => self.data.singles += 1,
+            2 => self.data.doubles += 1,
+            3 => self.data.triples += 1,
+            _ => self.data.tetrises += 1,
+        }
+        if t_spin != TSpin::None {
+            self.data.t_spins += 1;
+        }
+        if perfect_clear {
+            self.data.perfect_clears += 1;
+        }
+        if count > 0 {
+            self.data.lines += count;
+            self.data.garbage_lines += garbage_lines;
+            self.data.level = self.level_for(self.data.lines);
+        }
+        if self.line_goal().is_some_and(|goal| self.goal_lines() >= goal) {
+            self.game_over = Some(GameOverReason::Finished);
+        }
+        self.can_hold = true;
+        self.phase = if count > 0 {
+            Phase::LineClear { rows, timer: 0.0 }
+        } else {
+            Phase::Spawning { timer: 0.0 }
+        };
+        if !self.is_over() {
+            self.wait(0.0);
+        }
+    }
+
+    /// Tops the garbage of a dig race back up to `DIG_HEIGHT` rows, or half the visible
+    /// board if that is lower, for as long as the line goal leaves garbage to put in.
+    fn add_garbage(&mut self) {
+        if self.config.mode != GameMode::Dig {
+            return;
+        }
+        let goal = self.line_goal().unwrap_or_default();
+        let height = (DIG_HEIGHT as usize).min(self.board.visible_height() as usize / 2);
+        let mut rows = (0..self.board.height() as usize)
+            .filter(|&y| self.board.row(y).contains(&GARBAGE))
+            .count();
+        while rows < height && self.garbage_added < goal {
+            let bottom = self.board.row(0);
+            let previous_hole = if bottom.contains(&GARBAGE) {
+                bottom.iter().position(|&value| value == 0)
+            } else {
+                None
+            };
+            let row = self.config.garbage.row(self.board.width() as usize, previous_hole, &mut self.garbage_rng);
+            if !self.board.push_bottom(&row) {
+                self.game_over = Some(GameOverReason::TopOut);
+                return;
+            }
+            self.garbage_added += 1;
+            rows += 1;
+        }
+    }
+
+    fn next_tetramino_type(&mut self) -> TetraminoType {
+        self.randomizer.next(&mut self.rng)
+    }
+
+    fn fill_queue(&mut self) {
+        let preview_count = self.config.preview_count.clamp(MIN_PREVIEW, MAX_PREVIEW);
+        while self.queue.len() < preview_count {
+            let tetramino_type = self.next_tetramino_type();
+            self.queue.push_back(tetramino_type);
+        }
+    }
+
+    fn spawn(&mut self) {
+        self.fill_queue();
+        let tetramino_type = self.queue.pop_front().unwrap();
+        self.fill_queue();
+        self.spawn_tetramino(tetramino_type);
+    }
+
+    fn spawn_tetramino(&mut self, tetramino_type: TetraminoType) {
+        let mut tetramino = Tetramino::new();
+        tetramino.set_shape(&tetramino_type);
+        (tetramino.x, tetramino.y) = self.board.spawn_position();
+        self.active = tetramino;
+        self.lock_timer = 0.0;
+        self.lock_resets = 0;
+        self.lowest_y = tetramino.y;
+        self.last_kick = None;
+        self.das_cut_timer = self.config.das_cut;
+        if self.is_collided(&self.active) {
+            self.game_over = Some(GameOverReason::BlockOut);
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+#[cfg(test)]
+#[test]
+fn test_step_moves_on_press_only() {
+    let mut game = Game::new();
+    game.spawn_tetramino(TetraminoType::O);
+    let x = game.active.x;
+    let input = PlayerInput { left: true, ..Default::default() };
+    game.step(&input, 0.0);
+    game.step(&input, 0.0);
+    assert_eq!(game.active.x, x - 1);
+    game.step(&PlayerInput::default(), 0.0);
+    game.step(&input, 0.0);
+    assert_eq!(game.active.x, x - 2);
+}
+
+#[cfg(test)]
+#[test]
+fn test_das_repeats_held_moves() {
+    let mut game = Game::new();
+    game.spawn_tetramino(TetraminoType::O);
+    let x = game.active.x;
+    let (das, arr) = (game.config.das, game.config.arr);
+    let input = PlayerInput { left: true, ..Default::default() };
+    game.step(&input, 0.0);
+    game.step(&input, das * 0.9);
+    assert_eq!(game.active.x, x - 1);
+    game.step(&input, das * 0.1);
+    assert_eq!(game.active.x, x - 2);
+    game.step(&input, arr * 2.0);
+    assert_eq!(game.active.x, x - 4);
+    // Pressing right while left is still held turns around at once.
+    game.step(&PlayerInput { right: true, ..input }, 0.0);
+    assert_eq!(game.active.x, x - 3);
+}
+
+#[cfg(test)]
+#[test]
+fn test_zero_arr_keeps_das_charged_across_pieces() {
+    let mut game = Game::with_config(GameConfig { arr: 0.0, ..Default::default() });
+    let input = PlayerInput { left: true, ..Default::default() };
+    game.step(&input, 0.0);
+    game.step(&input, game.config.das);
+    let (min_x, _, _, _) = game.active.get_bounds();
+    assert_eq!(game.active.x + min_x, 0);
+    game.step(&PlayerInput { hard_drop: true, ..input }, 0.0);
+    game.step(&input, game.config.spawn_delay);
+    assert_eq!(game.phase, Phase::Falling);
+    game.step(&input, 0.0);
+    let (min_x, _, _, _) = game.active.get_bounds();
+    assert_eq!(game.active.x + min_x, 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_das_cut_delays_auto_shift_after_spawn() {
+    let mut game = Game::with_config(GameConfig { arr: 0.0, das_cut: 0.1, ..Default::default() });
+    let input = PlayerInput { left: true, ..Default::default() };
+    game.step(&input, 0.0);
+    game.step(&input, game.config.das + 0.1);
+    game.spawn_tetramino(TetraminoType::O);
+    let x = game.active.x;
+    game.step(&input, 0.05);
+    assert_eq!(game.active.x, x);
+    game.step(&input, 0.06);
+    let (min_x, _, _, _) = game.active.get_bounds();
+    assert_eq!(game.active.x + min_x, 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_walls_stop_movement() {
+    let mut game = Game::new();
+    game.spawn_tetramino(TetraminoType::O);
+    for _ in 0..COLS {
+        game.try_move(-1, 0);
+    }
+    let (min_x, _, _, _) = game.active.get_bounds();
+    assert_eq!(game.active.x + min_x, 0);
+    assert!(!game.try_move(-1, 0));
+}
+
+#[cfg(test)]
+#[test]
+fn test_gravity_locks_and_clears() {
+    let mut game = Game::new();
+    for x in 4..COLS as i32 {
+        game.board.set(x, 0, 1);
+    }
+    game.board.set(5, 1, 2);
+    game.spawn_tetramino(TetraminoType::I);
+    game.active.x = 0;
+    let fall_steps = ROWS as i32 + 3;
+    game.step(&PlayerInput::default(), game.fall_interval() * fall_steps as f32);
+    assert_eq!(game.data.score, 100);
+    assert_eq!(game.data.lines, 1);
+    // The full row stays until the line clear delay is over.
+    assert_eq!(game.board.get(0, 0), TetraminoType::I.value());
+    game.step(&PlayerInput::default(), game.config.line_clear_delay);
+    assert_eq!(game.board.get(5, 0), 2);
+    assert_eq!(game.board.get(0, 0), 0);
+    assert_eq!(game.board.get(5, 1), 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_pieces_spawn_in_the_middle_of_any_board() {
+    let mut game = Game::with_config(GameConfig { width: 4, height: 8, ..Default::default() });
+    game.spawn_tetramino(TetraminoType::I);
+    assert_eq!((game.active.x, game.active.y), (0, 6));
+    game.step(&PlayerInput { hard_drop: true, ..Default::default() }, 0.0);
+    assert_eq!(game.data.lines, 1);
+
+    let game = Game::with_config(GameConfig { width: 16, height: 40, ..Default::default() });
+    assert_eq!((game.active.x, game.active.y), (6, 38));
+}
+
+#[cfg(test)]
+#[test]
+fn test_seed_makes_games_reproducible() {
+    let deal = |seed: u64| {
+        let mut game = Game::with_config(GameConfig { seed: Some(seed), ..Default::default() });
+        (0..50).map(|_| game.next_tetramino_type()).collect::<Vec<_>>()
+    };
+    assert_eq!(deal(42), deal(42));
+    assert_ne!(deal(42), deal(43));
+
+    let mut game = Game::with_config(GameConfig { seed: Some(42), ..Default::default() });
+    let first = (game.active, game.queue.clone());
+    game.step(&PlayerInput::default(), game.fall_interval() * 100.0);
+    game.restart();
+    assert_eq!(game.seed, 42);
+    assert_eq!((game.active, game.queue.clone()), first);
+}
+
+#[cfg(test)]
+#[test]
+fn test_pieces_spawn_from_the_front_of_the_queue() {
+    for preview_count in [0, 1, 3, 6, 10] {
+        let mut game = Game::with_config(GameConfig { preview_count, spawn_delay: 0.0, ..Default::default() });
+        let expected = preview_count.clamp(MIN_PREVIEW, MAX_PREVIEW);
+        assert_eq!(game.queue.len(), expected);
+        for _ in 0..10 {
+            let next = game.queue[0];
+            game.hard_drop();
+            assert_eq!(game.active.tetramino_type, next);
+            assert_eq!(game.queue.len(), expected);
+            game.board.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_hold_swaps_once_per_piece() {
+    let mut game = Game::new();
+    let first = game.active.tetramino_type;
+    let next = game.queue[0];
+    game.try_move(1, -3);
+    assert!(game.try_hold());
+    assert_eq!(game.hold, Some(first));
+    assert_eq!(game.active.tetramino_type, next);
+    assert!(!game.try_hold());
+    assert_eq!(game.active.tetramino_type, next);
+
+    game.hard_drop();
+    game.board.clear();
+    let third = game.active.tetramino_type;
+    game.try_rotate(RotationDirection::Clockwise);
+    assert!(game.try_hold());
+    assert_eq!(game.hold, Some(third));
+    let mut spawned = Tetramino::new();
+    spawned.set_shape(&first);
+    (spawned.x, spawned.y) = game.board.spawn_position();
+    assert_eq!(game.active, spawned);
+}
+
+#[cfg(test)]
+#[test]
+fn test_hold_into_a_blocked_spawn_ends_the_step() {
+    let mut game = Game::new();
+    for x in 3..7 {
+        for y in 0..ROWS as i32 + 2 {
+            game.board.set(x, y, 1);
+        }
+    }
+    let board = game.board.clone();
+    game.step(&PlayerInput { hold: true, hard_drop: true, ..Default::default() }, 0.0);
+    assert_eq!(game.game_over, Some(GameOverReason::BlockOut));
+    assert_eq!(game.board, board);
+    assert_eq!(game.data.pieces, 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_hard_drop_locks_at_the_bottom() {
+    let mut game = Game::new();
+    game.spawn_tetramino(TetraminoType::O);
+    let distance = game.drop_distance();
+    assert_eq!(distance, ROWS as i32);
+    let ghost = game.ghost();
+    assert_eq!(ghost.y, game.active.y - distance);
+    game.step(&PlayerInput { hard_drop: true, ..Default::default() }, 0.0);
+    assert_eq!(game.data.score, 2 * distance);
+    for (x, y) in [(4, 0), (5, 0), (4, 1), (5, 1)] {
+        assert_eq!(game.board.get(x, y), TetraminoType::O.value());
+    }
+    assert!(game.can_hold);
+}
+
+#[cfg(test)]
+#[test]
+fn test_soft_drop_falls_faster_and_scores() {
+    let mut game = Game::new();
+    game.spawn_tetramino(TetraminoType::O);
+    let y = game.active.y;
+    let input = PlayerInput { soft_drop: true, ..Default::default() };
+    let interval = game.fall_interval() / game.config.soft_drop_factor;
+    game.step(&input, interval * 5.5);
+    assert_eq!(game.active.y, y - 5);
+    assert_eq!(game.data.score, 5);
+    game.step(&PlayerInput::default(), interval * 5.0);
+    assert_eq!(game.active.y, y - 5);
+}
+
+#[cfg(test)]
+#[test]
+fn test_level_goes_up_every_ten_lines() {
+    let mut game = Game::new();
+    let slow = game.fall_interval();
+    for _ in 0..5 {
+        for y in 0..2 {
+            for x in 0..COLS as i32 {
+                game.board.set(x, y, 1);
+            }
+        }
+        game.active.y -= game.drop_distance();
+        game.lock();
+    }
+    assert_eq!(game.data.lines, 10);
+    assert_eq!(game.data.doubles, 5);
+    assert_eq!(game.data.level, 2);
+    assert!(game.fall_interval() < slow);
+}
+
+#[cfg(test)]
+#[test]
+fn test_20g_drops_to_the_floor_in_one_step() {
+    let mut game = Game::new();
+    game.data.level = 20;
+    game.spawn_tetramino(TetraminoType::O);
+    game.step(&PlayerInput::default(), 1.0 / 60.0);
+    assert_eq!(game.active.tetramino_type, TetraminoType::O);
+    assert!(game.is_grounded());
+}
+
+#[cfg(test)]
+fn o_locked(game: &Game) -> bool {
+    (0..COLS as i32).any(|x| game.board.get(x, 0) == TetraminoType::O.value())
+}
+
+#[cfg(test)]
+fn shuffle_on_the_floor(lock_reset: LockReset, steps: usize) -> Game {
+    let mut game = Game::with_config(GameConfig { lock_reset, ..Default::default() });
+    game.spawn_tetramino(TetraminoType::O);
+    game.active.y -= game.drop_distance();
+    let left = PlayerInput { left: true, ..Default::default() };
+    let right = PlayerInput { right: true, ..Default::default() };
+    let dt = game.config.lock_delay * 0.75;
+    for step in 0..steps {
+        game.step(if step % 2 == 0 { &left } else { &right }, dt);
+    }
+    game
+}
+
+#[cfg(test)]
+#[test]
+fn test_lock_delay_waits_before_locking() {
+    let mut game = Game::new();
+    game.spawn_tetramino(TetraminoType::O);
+    game.active.y -= game.drop_distance();
+    let delay = game.config.lock_delay;
+    game.step(&PlayerInput::default(), delay * 0.5);
+    assert!(!o_locked(&game));
+    game.step(&PlayerInput::default(), delay * 0.5);
+    assert!(o_locked(&game));
+}
+
+#[cfg(test)]
+#[test]
+fn test_lock_reset_rules() {
+    // Each step takes three quarters of the delay, so only resets keep the piece alive.
+    let game = shuffle_on_the_floor(LockReset::Infinite, 100);
+    assert!(!o_locked(&game));
+    let game = shuffle_on_the_floor(LockReset::Extended, MAX_LOCK_RESETS as usize);
+    assert!(!o_locked(&game));
+    let game = shuffle_on_the_floor(LockReset::Extended, MAX_LOCK_RESETS as usize + 2);
+    assert!(o_locked(&game));
+    let game = shuffle_on_the_floor(LockReset::Classic, 2);
+    assert!(o_locked(&game));
+}
+
+#[cfg(test)]
+#[test]
+fn test_t_spin_double_is_scored_and_reported() {
+    let mut game = Game::new();
+    //   y=2  . . . X . X . . . .
+    //   y=1  X X X . . . X X X X
+    //   y=0  X X X X . X X X X X
+    for x in 0..COLS as i32 {
+        if x != 4 {
+            game.board.set(x, 0, 1);
+        }
+        if !(3..=5).contains(&x) {
+            game.board.set(x, 1, 1);
+        }
+    }
+    game.board.set(3, 2, 1);
+    game.board.set(5, 2, 1);
+    game.spawn_tetramino(TetraminoType::T);
+    game.active.rotate(RotationDirection::Half);
+    game.active.x = 3;
+    game.active.y = -1;
+    assert!(!game.is_collided(&game.active));
+    assert_eq!(game.t_spin(), TSpin::None);
+    game.last_kick = Some((RotationDirection::Half, 0));
+    assert_eq!(game.t_spin(), TSpin::Full);
+
+    game.lock();
+    let events = game.score_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].clear, game.last_clear.unwrap());
+    assert_eq!(events[0].clear.lines, 2);
+    assert_eq!(events[0].clear.t_spin, TSpin::Full);
+    assert_eq!(game.data.score, 1200);
+    assert_eq!(game.data.t_spins, 1);
+    game.step(&PlayerInput::default(), 0.0);
+    assert!(game.score_events().is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_next_piece_waits_for_line_clear_and_spawn_delays() {
+    let mut game = Game::new();
+    for x in 0..COLS as i32 - 4 {
+        game.board.set(x, 0, 1);
+    }
+    game.spawn_tetramino(TetraminoType::I);
+    game.active.x = COLS as i32 - 4;
+    let next = game.queue[0];
+    let left = PlayerInput { left: true, ..Default::default() };
+    game.step(&PlayerInput { hard_drop: true, ..Default::default() }, 0.0);
+    assert_eq!(game.phase, Phase::LineClear { rows: vec![0], timer: 0.0 });
+    game.step(&left, game.config.line_clear_delay);
+    assert_eq!(game.phase, Phase::Spawning { timer: 0.0 });
+    assert!(game.board.is_empty());
+    // Held during the delays, left charges DAS but moves nothing.
+    game.step(&left, game.config.spawn_delay);
+    assert_eq!(game.phase, Phase::Falling);
+    assert_eq!(game.active.tetramino_type, next);
+    assert!(game.das_timer > 0.0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_block_out_ends_the_game() {
+    let mut game = Game::new();
+    for x in 3..7 {
+        for y in 0..ROWS as i32 + 2 {
+            game.board.set(x, y, 1);
+        }
+    }
+    game.spawn_tetramino(TetraminoType::O);
+    assert_eq!(game.game_over, Some(GameOverReason::BlockOut));
+
+    let active = game.active;
+    let score = game.data.score;
+    game.step(&PlayerInput { left: true, ..Default::default() }, game.fall_interval() * 4.0);
+    assert_eq!(game.active, active);
+    assert_eq!(game.data.score, score);
+
+    game.restart();
+    assert!(!game.is_over());
+    assert_eq!(game.board, Board::new());
+    assert_eq!(game.data.score, 0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_stack_grows_into_the_buffer() {
+    let mut game = Game::new();
+    for y in 0..ROWS as i32 - 2 {
+        game.board.set(5, y, 1);
+    }
+    // Turned upright in the spawn rows, the I lands across the skyline, half of it hidden.
+    game.spawn_tetramino(TetraminoType::I);
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    game.step(&PlayerInput { hard_drop: true, ..Default::default() }, 0.0);
+    assert!(!game.is_over());
+    assert_eq!(game.board.get(5, ROWS as i32 + 1), TetraminoType::I.value());
+    assert!(game.board.is_hidden(ROWS as i32 + 1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_lock_out_ends_the_game() {
+    let mut game = Game::new();
+    for x in 0..COLS as i32 {
+        if x != 0 {
+            game.board.set(x, ROWS as i32 - 1, 1);
+        }
+    }
+    game.spawn_tetramino(TetraminoType::O);
+    game.step(&PlayerInput { hard_drop: true, ..Default::default() }, 0.0);
+    assert_eq!(game.game_over, Some(GameOverReason::LockOut));
+}
+
+#[cfg(test)]
+#[test]
+fn test_sprint_finishes_on_the_line_goal() {
+    let mut game = Game::with_config(GameConfig { mode: GameMode::Sprint, line_goal: LineGoal::Lines(2), ..Default::default() });
+    assert_eq!(game.line_goal(), Some(2));
+    assert_eq!(Game::with_config(GameConfig { mode: GameMode::Sprint, ..Default::default() }).line_goal(), Some(SPRINT_LINES));
+    assert_eq!(Game::new().line_goal(), None);
+    for y in 0..2 {
+        for x in 0..COLS as i32 - 1 {
+            game.board.set(x, y, 1);
+        }
+    }
+    game.spawn_tetramino(TetraminoType::I);
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    // Upright in the third column of its box, one column left of the gap.
+    game.active.x = COLS as i32 - 4;
+    game.step(&PlayerInput { right: true, ..Default::default() }, 0.0);
+    game.step(&PlayerInput::default(), 0.0);
+    game.step(&PlayerInput { hard_drop: true, ..Default::default() }, 0.5);
+    assert_eq!(game.data.lines, 2);
+    assert_eq!(game.game_over, Some(GameOverReason::Finished));
+    assert_eq!(game.data.pieces, 1);
+    assert_eq!(game.data.keys, 2);
+    game.step(&PlayerInput::default(), 1.0);
+    assert_eq!(game.data.time, Duration::from_secs_f32(0.5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_timer_starts_on_the_first_press() {
+    let mut game = Game::new();
+    game.step(&PlayerInput::default(), 1.0);
+    assert_eq!(game.data.time, Duration::ZERO);
+    game.step(&PlayerInput { soft_drop: true, ..Default::default() }, 1.0);
+    assert_eq!(game.data.time, Duration::ZERO);
+    game.step(&PlayerInput::default(), 0.25);
+    game.step(&PlayerInput::default(), 0.25);
+    assert_eq!(game.data.time, Duration::from_millis(500));
+    assert_eq!(game.data.keys, 1);
+}
+
+#[cfg(test)]
+#[test]
+fn test_ultra_ends_when_the_time_runs_out() {
+    let mut game = Game::with_config(GameConfig { mode: GameMode::Ultra, time_limit: Some(3), ..Default::default() });
+    assert_eq!(game.time_limit(), Some(Duration::from_secs(3)));
+    assert_eq!(Game::with_config(GameConfig { mode: GameMode::Ultra, ..Default::default() }).time_limit(),
+               Some(Duration::from_secs(ULTRA_SECONDS.into())));
+    assert_eq!(Game::new().time_limit(), None);
+    let left = PlayerInput { left: true, ..Default::default() };
+    game.step(&left, 0.0);
+    game.step(&left, 2.0);
+    assert_eq!(game.time_left(), Some(Duration::from_secs(1)));
+    assert!(!game.is_over());
+    game.step(&left, 2.0);
+    assert_eq!(game.game_over, Some(GameOverReason::Finished));
+    assert_eq!(game.time_left(), Some(Duration::ZERO));
+}
+
+#[cfg(test)]
+#[test]
+fn test_marathon_starts_at_the_start_level_and_caps_it() {
+    let config = GameConfig { mode: GameMode::Marathon, start_level: 15, ..Default::default() };
+    let mut game = Game::with_config(config);
+    assert_eq!(game.data.level, 15);
+    assert_eq!(game.line_goal(), Some(MARATHON_LINES[0]));
+    game.config.line_goal = LineGoal::Unlimited;
+    assert_eq!(game.line_goal(), None);
+    game.config.mode = GameMode::Sprint;
+    assert_eq!(game.line_goal(), Some(SPRINT_LINES));
+    game.config.mode = GameMode::Marathon;
+    assert_eq!(game.level_for(49), 19);
+    assert_eq!(game.level_for(140), MARATHON_MAX_LEVEL);
+    game.config.mode = GameMode::Endless;
+    assert_eq!(game.level_for(140), 29);
+    game.restart();
+    assert_eq!(game.data.level, 15);
+    game.config.mode = GameMode::Sprint;
+    game.restart();
+    assert_eq!(game.data.level, 1);
+}
+
+#[cfg(test)]
+fn garbage_rows(game: &Game) -> usize {
+    (0..game.board.height() as usize).filter(|&y| game.board.row(y).contains(&GARBAGE)).count()
+}
+
+/// Plugs the hole of the bottom row and locks an O on the stack to clear it.
+#[cfg(test)]
+fn dig_bottom_row(game: &mut Game) {
+    let hole = game.board.row(0).iter().position(|&value| value == 0).unwrap();
+    game.board.set(hole as i32, 0, 1);
+    game.spawn_tetramino(TetraminoType::O);
+    game.hard_drop();
+    game.step(&PlayerInput::default(), game.config.line_clear_delay);
+}
+
+#[cfg(test)]
+#[test]
+fn test_dig_keeps_the_garbage_topped_up() {
+    assert_eq!(Game::with_config(GameConfig { mode: GameMode::Dig, ..Default::default() }).line_goal(), Some(DIG_LINES[0]));
+    let config = GameConfig { mode: GameMode::Dig, line_goal: LineGoal::Lines(DIG_LINES[1]), seed: Some(3), ..Default::default() };
+    let mut game = Game::with_config(config);
+    assert_eq!(garbage_rows(&game), DIG_HEIGHT as usize);
+    assert!((0..DIG_HEIGHT as usize).all(|y| game.board.row(y).iter().filter(|&&value| value == 0).count() == 1));
+    dig_bottom_row(&mut game);
+    assert_eq!((game.data.lines, game.data.garbage_lines), (1, 1));
+    assert_eq!(garbage_rows(&game), DIG_HEIGHT as usize);
+    assert!(!game.is_over());
+}
+
+#[cfg(test)]
+#[test]
+fn test_dig_finishes_once_the_garbage_is_dug() {
+    let mut game = Game::with_config(GameConfig { mode: GameMode::Dig, line_goal: LineGoal::Lines(3), ..Default::default() });
+    assert_eq!(garbage_rows(&game), 3);
+    dig_bottom_row(&mut game);
+    assert_eq!(garbage_rows(&game), 2);
+    dig_bottom_row(&mut game);
+    assert!(!game.is_over());
+    dig_bottom_row(&mut game);
+    assert_eq!(game.goal_lines(), 3);
+    assert_eq!(game.game_over, Some(GameOverReason::Finished));
+}
@@ -0,0 +1,172 @@
+//! Game modes and the goals that end them.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Lines to clear in a sprint unless `GameConfig::line_goal` asks for another target.
+pub const SPRINT_LINES: u32 = 40;
+
+/// Line goals of a marathon, the guideline one first. The default is the first, and
+/// `LineGoal::Unlimited` plays a marathon with no goal at all.
+pub const MARATHON_LINES: [u32; 2] = [150, 200];
+
+/// Highest level of a marathon, it stays there until the goal whatever the start level.
+pub const MARATHON_MAX_LEVEL: u32 = 20;
+
+/// Garbage line goals of a dig race, the default first.
+pub const DIG_LINES: [u32; 3] = [10, 18, 100];
+
+/// Rows of garbage a dig race keeps on the board until the last of its garbage is in.
+pub const DIG_HEIGHT: u32 = 10;
+
+const MARATHON_GOALS: [LineGoal; 3] =
+    [LineGoal::Lines(MARATHON_LINES[0]), LineGoal::Lines(MARATHON_LINES[1]), LineGoal::Unlimited];
+const SPRINT_GOALS: [LineGoal; 1] = [LineGoal::Lines(SPRINT_LINES)];
+const DIG_GOALS: [LineGoal; 3] = [LineGoal::Lines(DIG_LINES[0]), LineGoal::Lines(DIG_LINES[1]), LineGoal::Lines(DIG_LINES[2])];
+
+/// Seconds an ultra game lasts unless `GameConfig::time_limit` asks for another limit.
+pub const ULTRA_SECONDS: u32 = 120;
+
+/// What the player is playing for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum GameMode {
+    /// Play for score until the stack tops out.
+    #[default]
+    Endless,
+    /// Play for score up to a line goal, or with none until the stack tops out, the level
+    /// capped at `MARATHON_MAX_LEVEL`.
+    Marathon,
+    /// Clear a number of lines as fast as possible.
+    Sprint,
+    /// Score as much as possible before the time runs out.
+    Ultra,
+    /// Dig through a number of garbage lines as fast as possible.
+    Dig,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] =
+        [GameMode::Endless, GameMode::Marathon, GameMode::Sprint, GameMode::Ultra, GameMode::Dig];
+
+    /// Line goals the menu offers for this mode, the default first. Empty for modes that
+    /// do not end on lines. A dig race counts garbage lines only.
+    pub fn line_goals(self) -> &'static [LineGoal] {
+        match self {
+            GameMode::Marathon => &MARATHON_GOALS,
+            GameMode::Sprint => &SPRINT_GOALS,
+            GameMode::Dig => &DIG_GOALS,
+            GameMode::Endless | GameMode::Ultra => &[],
+        }
+    }
+
+    /// Lines that finish a game of this mode, unless `GameConfig::line_goal` says
+    /// otherwise. `None` for modes that do not end on lines.
+    pub fn default_line_goal(self) -> Option<u32> {
+        match self.line_goals().first() {
+            Some(&LineGoal::Lines(lines)) => Some(lines),
+            _ => None,
+        }
+    }
+
+    /// Whether games of this mode honour `GameConfig::start_level`. The others always
+    /// start at level 1.
+    pub fn has_start_level(self) -> bool {
+        matches!(self, GameMode::Endless | GameMode::Marathon)
+    }
+
+    /// Highest level a game of this mode reaches, `None` if levels go on forever.
+    pub fn level_cap(self) -> Option<u32> {
+        match self {
+            GameMode::Marathon => Some(MARATHON_MAX_LEVEL),
+            GameMode::Endless | GameMode::Sprint | GameMode::Ultra | GameMode::Dig => None,
+        }
+    }
+
+    /// Seconds a game of this mode lasts, unless `GameConfig::time_limit` says otherwise.
+    /// `None` for modes without a time limit.
+    pub fn default_time_limit(self) -> Option<u32> {
+        match self {
+            GameMode::Ultra => Some(ULTRA_SECONDS),
+            GameMode::Endless | GameMode::Marathon | GameMode::Sprint | GameMode::Dig => None,
+        }
+    }
+
+    /// The mode after this one in `ALL`, wrapping around.
+    pub fn next(self) -> Self {
+        let index = GameMode::ALL.iter().position(|&mode| mode == self).unwrap_or_default();
+        GameMode::ALL[(index + 1) % GameMode::ALL.len()]
+    }
+}
+
+/// Line goal picked for a game, see `Game::line_goal` for the lines it comes to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LineGoal {
+    /// The first of `GameMode::line_goals`.
+    #[default]
+    Default,
+    Lines(u32),
+    /// No goal, the game goes on until the stack tops out. Only modes listing it in
+    /// `GameMode::line_goals` allow it, the others keep their default.
+    Unlimited,
+}
+
+impl FromStr for LineGoal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(LineGoal::Unlimited),
+            _ => match s.parse() {
+                Ok(lines) if lines > 0 => Ok(LineGoal::Lines(lines)),
+                _ => Err(format!("invalid line goal `{}`, expected a positive number of lines or none", s)),
+            },
+        }
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "endless" => Ok(GameMode::Endless),
+            "marathon" => Ok(GameMode::Marathon),
+            "sprint" | "40l" => Ok(GameMode::Sprint),
+            "ultra" => Ok(GameMode::Ultra),
+            "dig" | "cheese" => Ok(GameMode::Dig),
+            _ => Err(format!("unknown mode `{}`, expected endless, marathon, sprint, ultra or dig", s)),
+        }
+    }
+}
+
+impl fmt::Display for GameMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            GameMode::Endless => "endless",
+            GameMode::Marathon => "marathon",
+            GameMode::Sprint => "sprint",
+            GameMode::Ultra => "ultra",
+            GameMode::Dig => "dig",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_game_mode_from_str() {
+    for mode in GameMode::ALL {
+        assert_eq!(mode.to_string().parse::<GameMode>(), Ok(mode));
+    }
+    assert_eq!("40l".parse::<GameMode>(), Ok(GameMode::Sprint));
+    assert!("versus".parse::<GameMode>().is_err());
+    assert_eq!(GameMode::Sprint.next(), GameMode::Ultra);
+    assert_eq!("cheese".parse::<GameMode>(), Ok(GameMode::Dig));
+    assert_eq!(GameMode::Ultra.next(), GameMode::Dig);
+    assert_eq!(GameMode::Dig.next(), GameMode::Endless);
+    assert_eq!(GameMode::Dig.default_line_goal(), Some(DIG_LINES[0]));
+    assert_eq!(GameMode::Ultra.default_line_goal(), None);
+    assert_eq!("none".parse::<LineGoal>(), Ok(LineGoal::Unlimited));
+    assert_eq!("150".parse::<LineGoal>(), Ok(LineGoal::Lines(150)));
+    assert!("0".parse::<LineGoal>().is_err());
+}
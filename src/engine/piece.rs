@@ -0,0 +1,376 @@
+
+// Shapes are indexed as `shape[x][y]` with `y` growing upwards, so each inner array is a
+// column of the piece and the literals read rotated. All of them are in the SRS spawn
+// orientation: JLSTZ take the top left 3x3 of the box, I and O are centred in its width.
+
+pub const I_TETRAMINO: [[u8; 4]; 4] = [
+    [0, 0, 1, 0],
+    [0, 0, 1, 0],
+    [0, 0, 1, 0],
+    [0, 0, 1, 0],
+];
+
+pub const S_TETRAMINO: [[u8; 4]; 4] = [
+    [0, 0, 1, 0],
+    [0, 0, 1, 1],
+    [0, 0, 0, 1],
+    [0, 0, 0, 0],
+];
+
+pub const Z_TETRAMINO: [[u8; 4]; 4] = [
+    [0, 0, 0, 1],
+    [0, 0, 1, 1],
+    [0, 0, 1, 0],
+    [0, 0, 0, 0],
+];
+
+pub const J_TETRAMINO: [[u8; 4]; 4] = [
+    [0, 0, 1, 1],
+    [0, 0, 1, 0],
+    [0, 0, 1, 0],
+    [0, 0, 0, 0],
+];
+
+pub const L_TETRAMINO: [[u8; 4]; 4] = [
+    [0, 0, 1, 0],
+    [0, 0, 1, 0],
+    [0, 0, 1, 1],
+    [0, 0, 0, 0],
+];
+
+pub const O_TETRAMINO: [[u8; 4]; 4] = [
+    [0, 0, 0, 0],
+    [0, 0, 1, 1],
+    [0, 0, 1, 1],
+    [0, 0, 0, 0],
+];
+
+pub const T_TETRAMINO: [[u8; 4]; 4] = [
+    [0, 0, 1, 0],
+    [0, 0, 1, 1],
+    [0, 0, 1, 0],
+    [0, 0, 0, 0],
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RotationDirection {
+    Clockwise,
+    Conterclockwise,
+    Half,
+}
+
+/// SRS rotation states: spawn (0), clockwise from spawn (R), upside down (2) and
+/// counterclockwise from spawn (L).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rotation {
+    Spawn,
+    Right,
+    Reverse,
+    Left,
+}
+
+impl Rotation {
+    pub fn clockwise(&self) -> Rotation {
+        match self {
+            Rotation::Spawn => Rotation::Right,
+            Rotation::Right => Rotation::Reverse,
+            Rotation::Reverse => Rotation::Left,
+            Rotation::Left => Rotation::Spawn,
+        }
+    }
+
+    pub fn conterclockwise(&self) -> Rotation {
+        match self {
+            Rotation::Spawn => Rotation::Left,
+            Rotation::Right => Rotation::Spawn,
+            Rotation::Reverse => Rotation::Right,
+            Rotation::Left => Rotation::Reverse,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Rotation::Spawn => 0,
+            Rotation::Right => 1,
+            Rotation::Reverse => 2,
+            Rotation::Left => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TetraminoType {
+    I,
+    S,
+    Z,
+    J,
+    L,
+    O,
+    T,
+}
+
+impl TetraminoType {
+    pub const ALL: [TetraminoType; 7] = [
+        TetraminoType::I,
+        TetraminoType::S,
+        TetraminoType::Z,
+        TetraminoType::J,
+        TetraminoType::L,
+        TetraminoType::O,
+        TetraminoType::T,
+    ];
+
+    /// Side of the square the piece rotates in. JLSTZ use the top left 3x3 of the shape,
+    /// I the whole 4x4 and O does not rotate at all.
+    pub fn box_size(&self) -> usize {
+        match self {
+            TetraminoType::I => 4,
+            TetraminoType::O => 0,
+            _ => 3,
+        }
+    }
+
+    /// Value stored in the board for a cell locked by this piece. Zero means empty.
+    pub fn value(&self) -> u8 {
+        match self {
+            TetraminoType::I => 1,
+            TetraminoType::S => 2,
+            TetraminoType::Z => 3,
+            TetraminoType::J => 4,
+            TetraminoType::L => 5,
+            TetraminoType::O => 6,
+            TetraminoType::T => 7,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Tetramino {
+    pub x: i32,
+    pub y: i32,
+    pub shape: [[u8; 4]; 4],
+    pub tetramino_type: TetraminoType,
+    pub rotation: Rotation,
+}
+
+impl Tetramino {
+    /// An I piece with the bottom left corner of its box at the origin. Pieces in play are
+    /// moved to `Board::spawn_position`.
+    pub fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            shape: [
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+                [0, 0, 0, 0],
+            ],
+            tetramino_type: TetraminoType::I,
+            rotation: Rotation::Spawn,
+        }
+    }
+
+    /// Rotates the shape a quarter turn clockwise inside its rotation box, without any
+    /// kicks. Use `Game::try_rotate` to rotate a piece on the board.
+    pub fn rotate_clockwise(&mut self) {
+        let size = self.tetramino_type.box_size();
+        if size > 0 {
+            let mut new_shape = [[0; 4]; 4];
+            for x in 0..size {
+                for y in 4 - size..4 {
+                    new_shape[y + size - 4][3 - x] = self.shape[x][y];
+                }
+            }
+            self.shape = new_shape;
+        }
+        self.rotation = self.rotation.clockwise();
+    }
+
+    pub fn rotate_conterclockwise(&mut self) {
+        let size = self.tetramino_type.box_size();
+        if size > 0 {
+            let mut new_shape = [[0; 4]; 4];
+            for x in 0..size {
+                for y in 4 - size..4 {
+                    new_shape[3 - y][4 - size + x] = self.shape[x][y];
+                }
+            }
+            self.shape = new_shape;
+        }
+        self.rotation = self.rotation.conterclockwise();
+    }
+
+    pub fn rotate_180(&mut self) {
+        self.rotate_clockwise();
+        self.rotate_clockwise();
+    }
+
+    pub fn rotate(&mut self, direction: RotationDirection) {
+        match direction {
+            RotationDirection::Clockwise => self.rotate_clockwise(),
+            RotationDirection::Conterclockwise => self.rotate_conterclockwise(),
+            RotationDirection::Half => self.rotate_180(),
+        }
+    }
+
+    pub fn get_bounds(&self) -> (i32, i32, i32, i32) {
+        let mut min_x: i32 = 3;
+        let mut min_y: i32 = 3;
+        let mut max_x: i32 = 0;
+        let mut max_y: i32 = 0;
+        for x in 0..4 {
+            for y in 0..4 {
+                if self.shape[x as usize][y as usize] == 1 {
+                    if x < min_x {
+                        min_x = x;
+                    }
+                    if x > max_x {
+                        max_x = x;
+                    }
+                    if y < min_y {
+                        min_y = y;
+                    }
+                    if y > max_y {
+                        max_y = y;
+                    }
+                }
+            }
+        }
+        (min_x, min_y, max_x, max_y)
+    }
+
+    pub fn set_shape(&mut self, tetramino_type: &TetraminoType) {
+        match tetramino_type {
+            TetraminoType::I => self.shape = I_TETRAMINO,
+            TetraminoType::S => self.shape = S_TETRAMINO,
+            TetraminoType::Z => self.shape = Z_TETRAMINO,
+            TetraminoType::J => self.shape = J_TETRAMINO,
+            TetraminoType::L => self.shape = L_TETRAMINO,
+            TetraminoType::O => self.shape = O_TETRAMINO,
+            TetraminoType::T => self.shape = T_TETRAMINO,
+        }
+        self.tetramino_type = *tetramino_type;
+        self.rotation = Rotation::Spawn;
+    }
+
+    /// Board coordinates of every filled cell of the shape.
+    pub fn cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (0..4).flat_map(move |x| (0..4).map(move |y| (x, y)))
+            .filter(move |&(x, y)| self.shape[x][y] == 1)
+            .map(move |(x, y)| (self.x + x as i32, self.y + y as i32))
+    }
+}
+
+impl Default for Tetramino {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_set_shape() {
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::I);
+    assert_eq!(tetramino.shape, I_TETRAMINO);
+    tetramino.set_shape(&TetraminoType::S);
+    assert_eq!(tetramino.shape, S_TETRAMINO);
+    tetramino.set_shape(&TetraminoType::Z);
+    assert_eq!(tetramino.shape, Z_TETRAMINO);
+    tetramino.set_shape(&TetraminoType::J);
+    assert_eq!(tetramino.shape, J_TETRAMINO);
+    tetramino.set_shape(&TetraminoType::L);
+    assert_eq!(tetramino.shape, L_TETRAMINO);
+    tetramino.set_shape(&TetraminoType::O);
+    assert_eq!(tetramino.shape, O_TETRAMINO);
+    tetramino.set_shape(&TetraminoType::T);
+    assert_eq!(tetramino.shape, T_TETRAMINO);
+}
+
+#[cfg(test)]
+#[test]
+fn test_rotate_clockwise() {
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::I);
+    tetramino.rotate_clockwise();
+    assert_eq!(tetramino.rotation, Rotation::Right);
+    assert_eq!(tetramino.shape, [
+        [0, 0, 0, 0],
+        [0, 0, 0, 0],
+        [1, 1, 1, 1],
+        [0, 0, 0, 0],
+    ]);
+    tetramino.set_shape(&TetraminoType::S);
+    tetramino.rotate_clockwise();
+    assert_eq!(tetramino.shape, [
+        [0, 0, 0, 0],
+        [0, 0, 1, 1],
+        [0, 1, 1, 0],
+        [0, 0, 0, 0],
+    ]);
+    tetramino.set_shape(&TetraminoType::T);
+    for _ in 0..4 {
+        tetramino.rotate_clockwise();
+    }
+    assert_eq!(tetramino.rotation, Rotation::Spawn);
+    assert_eq!(tetramino.shape, T_TETRAMINO);
+}
+
+#[cfg(test)]
+#[test]
+fn test_rotate_conterclockwise() {
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::I);
+    tetramino.rotate_conterclockwise();
+    assert_eq!(tetramino.rotation, Rotation::Left);
+    assert_eq!(tetramino.shape, [
+        [0, 0, 0, 0],
+        [1, 1, 1, 1],
+        [0, 0, 0, 0],
+        [0, 0, 0, 0],
+    ]);
+    tetramino.set_shape(&TetraminoType::S);
+    tetramino.rotate_conterclockwise();
+    assert_eq!(tetramino.shape, [
+        [0, 0, 1, 1],
+        [0, 1, 1, 0],
+        [0, 0, 0, 0],
+        [0, 0, 0, 0],
+    ]);
+    tetramino.rotate_clockwise();
+    assert_eq!(tetramino.shape, S_TETRAMINO);
+}
+
+#[cfg(test)]
+#[test]
+fn test_o_does_not_rotate() {
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::O);
+    tetramino.rotate_clockwise();
+    assert_eq!(tetramino.shape, O_TETRAMINO);
+    assert_eq!(tetramino.rotation, Rotation::Right);
+    tetramino.rotate_180();
+    assert_eq!(tetramino.shape, O_TETRAMINO);
+    assert_eq!(tetramino.rotation, Rotation::Left);
+}
+
+#[cfg(test)]
+#[test]
+fn test_create_tetramino() {
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::I);
+    assert_eq!(tetramino.shape, I_TETRAMINO);
+    assert_eq!((tetramino.x, tetramino.y), (0, 0));
+}
+
+#[cfg(test)]
+#[test]
+fn test_cells() {
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::I);
+    tetramino.x = 0;
+    tetramino.y = 0;
+    let cells: Vec<(i32, i32)> = tetramino.cells().collect();
+    assert_eq!(cells, vec![(0, 2), (1, 2), (2, 2), (3, 2)]);
+}
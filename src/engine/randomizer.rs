@@ -0,0 +1,220 @@
+//! Strategies for choosing the next piece.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+use super::piece::TetraminoType;
+
+pub trait Randomizer: Send + Sync {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetraminoType;
+}
+
+/// Deals every piece `copies` times in a shuffled bag before refilling it.
+pub struct BagRandomizer {
+    copies: usize,
+    bag: Vec<TetraminoType>,
+}
+
+impl BagRandomizer {
+    pub fn new(copies: usize) -> Self {
+        Self { copies, bag: Vec::new() }
+    }
+}
+
+impl Randomizer for BagRandomizer {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetraminoType {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend_from_slice(&TetraminoType::ALL);
+            }
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().unwrap()
+    }
+}
+
+/// Every piece is equally likely regardless of what came before.
+pub struct PureRandomizer;
+
+impl Randomizer for PureRandomizer {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetraminoType {
+        TetraminoType::ALL[rng.gen_range(0..TetraminoType::ALL.len())]
+    }
+}
+
+/// The NES generator: rolls an eight sided die and rerolls once, from the seven pieces
+/// only, when it lands on the dummy side or repeats the previous piece.
+#[derive(Default)]
+pub struct NesRandomizer {
+    previous: Option<TetraminoType>,
+}
+
+impl Randomizer for NesRandomizer {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetraminoType {
+        let roll = rng.gen_range(0..TetraminoType::ALL.len() + 1);
+        let mut tetramino_type = TetraminoType::ALL.get(roll).copied();
+        if tetramino_type.is_none() || tetramino_type == self.previous {
+            tetramino_type = Some(TetraminoType::ALL[rng.gen_range(0..TetraminoType::ALL.len())]);
+        }
+        self.previous = tetramino_type;
+        tetramino_type.unwrap()
+    }
+}
+
+/// The TGM generator: remembers the last four pieces and rolls up to four times for one
+/// that is not among them. The history starts full of Z and the first piece is never S,
+/// Z or O.
+pub struct TgmRandomizer {
+    history: VecDeque<TetraminoType>,
+    first: bool,
+}
+
+impl TgmRandomizer {
+    const ROLLS: usize = 4;
+
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::from([TetraminoType::Z; 4]),
+            first: true,
+        }
+    }
+}
+
+impl Default for TgmRandomizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Randomizer for TgmRandomizer {
+    fn next(&mut self, rng: &mut dyn RngCore) -> TetraminoType {
+        let tetramino_type = if self.first {
+            self.first = false;
+            *[TetraminoType::I, TetraminoType::J, TetraminoType::L, TetraminoType::T]
+                .choose(rng)
+                .unwrap()
+        } else {
+            let mut tetramino_type = TetraminoType::ALL[rng.gen_range(0..TetraminoType::ALL.len())];
+            for _ in 1..Self::ROLLS {
+                if !self.history.contains(&tetramino_type) {
+                    break;
+                }
+                tetramino_type = TetraminoType::ALL[rng.gen_range(0..TetraminoType::ALL.len())];
+            }
+            tetramino_type
+        };
+        self.history.pop_front();
+        self.history.push_back(tetramino_type);
+        tetramino_type
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RandomizerKind {
+    #[default]
+    Bag7,
+    Bag14,
+    Random,
+    Nes,
+    Tgm,
+}
+
+impl RandomizerKind {
+    pub fn create(&self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::Bag7 => Box::new(BagRandomizer::new(1)),
+            RandomizerKind::Bag14 => Box::new(BagRandomizer::new(2)),
+            RandomizerKind::Random => Box::new(PureRandomizer),
+            RandomizerKind::Nes => Box::new(NesRandomizer::default()),
+            RandomizerKind::Tgm => Box::new(TgmRandomizer::new()),
+        }
+    }
+}
+
+impl FromStr for RandomizerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "7bag" | "bag7" => Ok(RandomizerKind::Bag7),
+            "14bag" | "bag14" => Ok(RandomizerKind::Bag14),
+            "random" => Ok(RandomizerKind::Random),
+            "nes" => Ok(RandomizerKind::Nes),
+            "tgm" => Ok(RandomizerKind::Tgm),
+            _ => Err(format!("unknown randomizer `{}`, expected one of 7bag, 14bag, random, nes, tgm", s)),
+        }
+    }
+}
+
+impl fmt::Display for RandomizerKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            RandomizerKind::Bag7 => "7bag",
+            RandomizerKind::Bag14 => "14bag",
+            RandomizerKind::Random => "random",
+            RandomizerKind::Nes => "nes",
+            RandomizerKind::Tgm => "tgm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+use rand::{rngs::StdRng, SeedableRng};
+
+#[cfg(test)]
+fn count(pieces: &[TetraminoType], tetramino_type: TetraminoType) -> usize {
+    pieces.iter().filter(|&&piece| piece == tetramino_type).count()
+}
+
+#[cfg(test)]
+#[test]
+fn test_bags_deal_every_piece() {
+    let mut rng = StdRng::seed_from_u64(7);
+    for copies in [1, 2] {
+        let mut randomizer = BagRandomizer::new(copies);
+        for _ in 0..10 {
+            let bag: Vec<TetraminoType> = (0..7 * copies).map(|_| randomizer.next(&mut rng)).collect();
+            for tetramino_type in TetraminoType::ALL {
+                assert_eq!(count(&bag, tetramino_type), copies);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_tgm_never_starts_with_s_z_or_o() {
+    for seed in 0..50 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let first = TgmRandomizer::new().next(&mut rng);
+        assert!(![TetraminoType::S, TetraminoType::Z, TetraminoType::O].contains(&first));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_history_randomizers_repeat_less() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for kind in [RandomizerKind::Nes, RandomizerKind::Tgm] {
+        let mut randomizer = kind.create();
+        let pieces: Vec<TetraminoType> = (0..7000).map(|_| randomizer.next(&mut rng)).collect();
+        let repeats = pieces.windows(2).filter(|pair| pair[0] == pair[1]).count();
+        // A uniform pick repeats one time in seven, about 1000 times here.
+        assert!(repeats < 500, "{} repeated {} times", kind, repeats);
+        for tetramino_type in TetraminoType::ALL {
+            assert!(count(&pieces, tetramino_type) > 500);
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_randomizer_kind_from_str() {
+    for kind in [RandomizerKind::Bag7, RandomizerKind::Bag14, RandomizerKind::Random, RandomizerKind::Nes, RandomizerKind::Tgm] {
+        assert_eq!(kind.to_string().parse::<RandomizerKind>(), Ok(kind));
+    }
+    assert!("bag".parse::<RandomizerKind>().is_err());
+}
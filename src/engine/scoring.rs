@@ -0,0 +1,188 @@
+//! Guideline scoring of line clears and T-spins.
+
+use std::fmt;
+use super::tspin::TSpin;
+
+/// What locking a piece did to the board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct LineClear {
+    pub lines: u32,
+    pub t_spin: TSpin,
+    /// The board was left empty.
+    pub perfect_clear: bool,
+}
+
+impl LineClear {
+    /// Tetrises and T-spins that clear lines keep a back-to-back chain going.
+    pub fn is_difficult(&self) -> bool {
+        self.lines >= 4 || (self.t_spin != TSpin::None && self.lines > 0)
+    }
+}
+
+impl fmt::Display for LineClear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines = match self.lines {
+            0 => "",
+            1 => "single",
+            2 => "double",
+            3 => "triple",
+            _ => "tetris",
+        };
+        let name = match self.t_spin {
+            TSpin::None => lines.to_string(),
+            TSpin::Mini => format!("T-spin mini {}", lines),
+            TSpin::Full => format!("T-spin {}", lines),
+        };
+        let mut name = name.trim_end().to_string();
+        if let Some(first) = name.get_mut(0..1) {
+            first.make_ascii_uppercase();
+        }
+        write!(f, "{}", name)
+    }
+}
+
+/// Points awarded for one locked piece, with the parts that made them up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScoreEvent {
+    pub clear: LineClear,
+    pub level: u32,
+    /// Value of the clear itself from the guideline table, times the level.
+    pub base: i32,
+    /// Whether `base` was raised by half for a difficult clear following another one.
+    pub back_to_back: bool,
+    /// Clears in a row before this one, zero for the first clear of a chain.
+    pub combo: u32,
+    pub combo_bonus: i32,
+    pub perfect_clear_bonus: i32,
+    pub total: i32,
+}
+
+/// What scoring remembers from one clear to the next.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct ScoreState {
+    /// Whether the last clear was difficult, so the next difficult one gets the bonus.
+    pub back_to_back: bool,
+    /// Clears in a row so far, `None` once a piece locks without clearing.
+    pub combo: Option<u32>,
+}
+
+impl ScoreState {
+    /// Scores a piece that locked at `level`. Returns `None` when the placement is worth
+    /// nothing, which also ends the combo.
+    pub fn award(&mut self, clear: LineClear, level: u32) -> Option<ScoreEvent> {
+        let level_factor = level as i32;
+        let lines = clear.lines;
+        let base = match (clear.t_spin, lines) {
+            (TSpin::None, 0) => 0,
+            (TSpin::None, 1) => 100,
+            (TSpin::None, 2) => 300,
+            (TSpin::None, 3) => 500,
+            (TSpin::None, _) => 800,
+            (TSpin::Mini, 0) => 100,
+            (TSpin::Mini, 1) => 200,
+            (TSpin::Mini, _) => 400,
+            (TSpin::Full, 0) => 400,
+            (TSpin::Full, 1) => 800,
+            (TSpin::Full, 2) => 1200,
+            (TSpin::Full, _) => 1600,
+        };
+
+        let combo = if lines > 0 {
+            let combo = self.combo.map_or(0, |combo| combo + 1);
+            self.combo = Some(combo);
+            combo
+        } else {
+            self.combo = None;
+            0
+        };
+        if base == 0 {
+            return None;
+        }
+
+        let mut event = ScoreEvent {
+            clear,
+            level,
+            base: base * level_factor,
+            back_to_back: false,
+            combo,
+            combo_bonus: 50 * combo as i32 * level_factor,
+            perfect_clear_bonus: 0,
+            total: 0,
+        };
+        // A T-spin without lines neither starts nor breaks a back-to-back chain.
+        if lines > 0 {
+            let difficult = clear.is_difficult();
+            event.back_to_back = difficult && self.back_to_back;
+            self.back_to_back = difficult;
+        }
+        if event.back_to_back {
+            event.base = event.base * 3 / 2;
+        }
+        if clear.perfect_clear {
+            let bonus = match lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if event.back_to_back => 3200,
+                _ => 2000,
+            };
+            event.perfect_clear_bonus = bonus * level_factor;
+        }
+        event.total = event.base + event.combo_bonus + event.perfect_clear_bonus;
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_line_clear_names() {
+    let clear = LineClear { lines: 2, t_spin: TSpin::Mini, ..Default::default() };
+    assert_eq!(clear.to_string(), "T-spin mini double");
+    let clear = LineClear { t_spin: TSpin::Full, ..Default::default() };
+    assert_eq!(clear.to_string(), "T-spin");
+}
+
+#[cfg(test)]
+#[test]
+fn test_line_clears_scale_with_level() {
+    let mut state = ScoreState::default();
+    assert_eq!(state.award(LineClear { lines: 1, ..Default::default() }, 1).unwrap().total, 100);
+    state = ScoreState::default();
+    assert_eq!(state.award(LineClear { lines: 4, ..Default::default() }, 3).unwrap().total, 2400);
+    state = ScoreState::default();
+    assert_eq!(state.award(LineClear { lines: 2, t_spin: TSpin::Full, ..Default::default() }, 2).unwrap().total, 2400);
+    state = ScoreState::default();
+    assert_eq!(state.award(LineClear { t_spin: TSpin::Mini, ..Default::default() }, 1).unwrap().total, 100);
+    assert_eq!(state.award(LineClear { ..Default::default() }, 1), None);
+}
+
+#[cfg(test)]
+#[test]
+fn test_back_to_back_and_combo() {
+    let mut state = ScoreState::default();
+    let first = state.award(LineClear { lines: 4, ..Default::default() }, 1).unwrap();
+    assert!(!first.back_to_back);
+    assert_eq!(first.combo, 0);
+    // A T-spin with no lines keeps the back-to-back chain but ends the combo.
+    state.award(LineClear { t_spin: TSpin::Full, ..Default::default() }, 1);
+    let second = state.award(LineClear { lines: 4, ..Default::default() }, 1).unwrap();
+    assert!(second.back_to_back);
+    assert_eq!(second.combo, 0);
+    let third = state.award(LineClear { lines: 1, ..Default::default() }, 1).unwrap();
+    assert!(!third.back_to_back);
+    assert_eq!(third.combo, 1);
+    assert_eq!(third.total, 100 + 50);
+    // The single broke the chain.
+    assert!(!state.award(LineClear { lines: 2, t_spin: TSpin::Full, ..Default::default() }, 1).unwrap().back_to_back);
+    assert_eq!(second.total, 1200);
+}
+
+#[cfg(test)]
+#[test]
+fn test_perfect_clear_bonus() {
+    let mut state = ScoreState::default();
+    let event = state.award(LineClear { lines: 4, perfect_clear: true, ..Default::default() }, 2).unwrap();
+    assert_eq!(event.perfect_clear_bonus, 4000);
+    assert_eq!(event.total, 1600 + 4000);
+    assert_eq!(event.clear.to_string(), "Tetris");
+}
@@ -0,0 +1,181 @@
+//! Super Rotation System wall kicks. Offsets are `(dx, dy)` with `y` growing upwards and
+//! are tried in order until the rotated piece fits.
+
+use super::piece::{Rotation, TetraminoType};
+
+const NO_KICKS: [(i32, i32); 1] = [(0, 0)];
+
+const JLSTZ_SPAWN_RIGHT: [(i32, i32); 5] = [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+const JLSTZ_RIGHT_SPAWN: [(i32, i32); 5] = [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+const JLSTZ_RIGHT_REVERSE: [(i32, i32); 5] = [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+const JLSTZ_REVERSE_RIGHT: [(i32, i32); 5] = [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+const JLSTZ_REVERSE_LEFT: [(i32, i32); 5] = [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+const JLSTZ_LEFT_REVERSE: [(i32, i32); 5] = [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+const JLSTZ_LEFT_SPAWN: [(i32, i32); 5] = [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+const JLSTZ_SPAWN_LEFT: [(i32, i32); 5] = [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+
+const I_SPAWN_RIGHT: [(i32, i32); 5] = [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)];
+const I_RIGHT_SPAWN: [(i32, i32); 5] = [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)];
+const I_RIGHT_REVERSE: [(i32, i32); 5] = [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)];
+const I_REVERSE_RIGHT: [(i32, i32); 5] = [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)];
+const I_REVERSE_LEFT: [(i32, i32); 5] = [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)];
+const I_LEFT_REVERSE: [(i32, i32); 5] = [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)];
+const I_LEFT_SPAWN: [(i32, i32); 5] = [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)];
+const I_SPAWN_LEFT: [(i32, i32); 5] = [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)];
+
+// The guideline has no 180 degree rotation, these follow the widely used SRS+ table and
+// apply to every piece but O.
+const HALF_SPAWN_REVERSE: [(i32, i32); 6] = [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)];
+const HALF_REVERSE_SPAWN: [(i32, i32); 6] = [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)];
+const HALF_RIGHT_LEFT: [(i32, i32); 6] = [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)];
+const HALF_LEFT_RIGHT: [(i32, i32); 6] = [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)];
+
+/// Offsets to try, in order, when rotating a piece of `tetramino_type` from one state to
+/// another. The first offset is always `(0, 0)`, the unkicked rotation.
+pub fn kicks(tetramino_type: TetraminoType, from: Rotation, to: Rotation) -> &'static [(i32, i32)] {
+    if tetramino_type == TetraminoType::O {
+        return &NO_KICKS;
+    }
+    match (from, to) {
+        (Rotation::Spawn, Rotation::Reverse) => &HALF_SPAWN_REVERSE,
+        (Rotation::Reverse, Rotation::Spawn) => &HALF_REVERSE_SPAWN,
+        (Rotation::Right, Rotation::Left) => &HALF_RIGHT_LEFT,
+        (Rotation::Left, Rotation::Right) => &HALF_LEFT_RIGHT,
+        _ if tetramino_type == TetraminoType::I => match (from, to) {
+            (Rotation::Spawn, Rotation::Right) => &I_SPAWN_RIGHT,
+            (Rotation::Right, Rotation::Spawn) => &I_RIGHT_SPAWN,
+            (Rotation::Right, Rotation::Reverse) => &I_RIGHT_REVERSE,
+            (Rotation::Reverse, Rotation::Right) => &I_REVERSE_RIGHT,
+            (Rotation::Reverse, Rotation::Left) => &I_REVERSE_LEFT,
+            (Rotation::Left, Rotation::Reverse) => &I_LEFT_REVERSE,
+            (Rotation::Left, Rotation::Spawn) => &I_LEFT_SPAWN,
+            (Rotation::Spawn, Rotation::Left) => &I_SPAWN_LEFT,
+            _ => &NO_KICKS,
+        },
+        (Rotation::Spawn, Rotation::Right) => &JLSTZ_SPAWN_RIGHT,
+        (Rotation::Right, Rotation::Spawn) => &JLSTZ_RIGHT_SPAWN,
+        (Rotation::Right, Rotation::Reverse) => &JLSTZ_RIGHT_REVERSE,
+        (Rotation::Reverse, Rotation::Right) => &JLSTZ_REVERSE_RIGHT,
+        (Rotation::Reverse, Rotation::Left) => &JLSTZ_REVERSE_LEFT,
+        (Rotation::Left, Rotation::Reverse) => &JLSTZ_LEFT_REVERSE,
+        (Rotation::Left, Rotation::Spawn) => &JLSTZ_LEFT_SPAWN,
+        (Rotation::Spawn, Rotation::Left) => &JLSTZ_SPAWN_LEFT,
+        _ => &NO_KICKS,
+    }
+}
+
+#[cfg(test)]
+use super::{Game, Tetramino, RotationDirection, COLS};
+
+#[cfg(test)]
+fn place(game: &mut Game, tetramino_type: TetraminoType, x: i32, y: i32) {
+    game.board = Default::default();
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&tetramino_type);
+    tetramino.x = x;
+    tetramino.y = y;
+    game.active = tetramino;
+}
+
+#[cfg(test)]
+#[test]
+fn test_rotation_without_obstacles_is_not_kicked() {
+    let mut game = Game::new();
+    for tetramino_type in TetraminoType::ALL {
+        place(&mut game, tetramino_type, 3, 5);
+        for direction in [RotationDirection::Clockwise, RotationDirection::Conterclockwise, RotationDirection::Half] {
+            assert!(game.try_rotate(direction));
+            assert_eq!((game.active.x, game.active.y), (3, 5));
+        }
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_i_kicks_off_the_left_wall() {
+    let mut game = Game::new();
+    place(&mut game, TetraminoType::I, 0, 5);
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    // Vertical in the third column of its box, so it can slide until the box hangs over.
+    while game.try_move(-1, 0) {}
+    assert_eq!(game.active.x, -2);
+    // R -> 2: (0, 0) and (-1, 0) hit the wall, (2, 0) fits.
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    assert_eq!(game.active.rotation, Rotation::Reverse);
+    assert_eq!((game.active.x, game.active.y), (0, 5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_i_kicks_off_the_right_wall() {
+    let mut game = Game::new();
+    place(&mut game, TetraminoType::I, 0, 5);
+    assert!(game.try_rotate(RotationDirection::Conterclockwise));
+    while game.try_move(1, 0) {}
+    assert_eq!(game.active.x, COLS as i32 - 2);
+    // L -> 0: (0, 0) and (1, 0) hit the wall, (-2, 0) fits.
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    assert_eq!(game.active.rotation, Rotation::Spawn);
+    assert_eq!((game.active.x, game.active.y), (COLS as i32 - 4, 5));
+}
+
+#[cfg(test)]
+#[test]
+fn test_t_kicks_off_the_floor() {
+    let mut game = Game::new();
+    // Flat side of the T on the floor.
+    place(&mut game, TetraminoType::T, 3, -2);
+    assert!(!game.try_move(0, -1));
+    // 0 -> R: (0, 0) and (-1, 0) go through the floor, (-1, 1) fits.
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    assert_eq!(game.active.rotation, Rotation::Right);
+    assert_eq!((game.active.x, game.active.y), (2, -1));
+}
+
+#[cfg(test)]
+#[test]
+fn test_t_kicks_down_into_a_slot() {
+    let mut game = Game::new();
+    place(&mut game, TetraminoType::T, 4, 1);
+    //   y=3  X . . X .
+    //   y=2  X . . . X
+    //   y=1  X X . X X
+    //        4 5 6 7 8
+    for (x, y) in [(4, 3), (7, 3), (4, 2), (8, 2), (4, 1), (5, 1), (7, 1), (8, 1)] {
+        game.board.set(x, y, 1);
+    }
+    game.active.rotate_clockwise();
+    assert!(!game.is_collided(&game.active));
+    // R -> 2 fails in place and on (1, 0), (1, -1) drops it into the slot.
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    assert_eq!(game.active.rotation, Rotation::Reverse);
+    assert_eq!((game.active.x, game.active.y), (5, 0));
+}
+
+#[cfg(test)]
+#[test]
+fn test_blocked_rotation_keeps_the_piece() {
+    let mut game = Game::new();
+    place(&mut game, TetraminoType::J, 3, 5);
+    for x in 0..COLS as i32 {
+        for y in 0..20 {
+            if !game.active.cells().any(|cell| cell == (x, y)) {
+                game.board.set(x, y, 1);
+            }
+        }
+    }
+    let before = game.active;
+    assert!(!game.try_rotate(RotationDirection::Clockwise));
+    assert!(!game.try_rotate(RotationDirection::Half));
+    assert_eq!(game.active, before);
+}
+
+#[cfg(test)]
+#[test]
+fn test_o_rotation_never_moves() {
+    let mut game = Game::new();
+    place(&mut game, TetraminoType::O, 3, 5);
+    let cells: Vec<(i32, i32)> = game.active.cells().collect();
+    assert!(game.try_rotate(RotationDirection::Clockwise));
+    assert_eq!(game.active.cells().collect::<Vec<_>>(), cells);
+}
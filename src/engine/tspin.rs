@@ -0,0 +1,122 @@
+//! T-spin detection with the 3-corner rule.
+
+use super::board::Board;
+use super::piece::{Rotation, RotationDirection, Tetramino, TetraminoType};
+
+/// How the last piece was spun into place, `TSpin::None` for an ordinary placement.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TSpin {
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+/// Index in the quarter turn SRS kick lists of the last test, the one that lets a T go two
+/// rows down into a slot. It always counts as a full T-spin. The 180 degree lists are
+/// longer and have nothing like it at this index.
+const LAST_KICK: usize = 4;
+
+/// Classifies a T about to lock at `tetramino`. `last_kick` is the direction and the index
+/// of the SRS kick used when the last successful action on the piece was a rotation, `None`
+/// otherwise.
+///
+/// Three of the four cells diagonal to the T centre must be blocked, walls and floor
+/// included. It is a full T-spin when both corners on the pointing side are among them,
+/// or when the rotation needed the last kick, and a mini otherwise.
+pub fn detect(board: &Board, tetramino: &Tetramino, last_kick: Option<(RotationDirection, usize)>) -> TSpin {
+    let (direction, kick) = match last_kick {
+        Some(last_kick) if tetramino.tetramino_type == TetraminoType::T => last_kick,
+        _ => return TSpin::None,
+    };
+    // The T turns inside the top left 3x3 of its 4x4 box.
+    let (cx, cy) = (tetramino.x + 1, tetramino.y + 2);
+    let blocked = |(dx, dy): (i32, i32)| board.is_blocked(cx + dx, cy + dy);
+    let corners = [(-1, 1), (1, 1), (1, -1), (-1, -1)];
+    if corners.into_iter().filter(|&corner| blocked(corner)).count() < 3 {
+        return TSpin::None;
+    }
+    let front = match tetramino.rotation {
+        Rotation::Spawn => [(-1, 1), (1, 1)],
+        Rotation::Right => [(1, 1), (1, -1)],
+        Rotation::Reverse => [(1, -1), (-1, -1)],
+        Rotation::Left => [(-1, -1), (-1, 1)],
+    };
+    if front.into_iter().all(blocked) || (direction != RotationDirection::Half && kick == LAST_KICK) {
+        TSpin::Full
+    } else {
+        TSpin::Mini
+    }
+}
+
+#[cfg(test)]
+use super::COLS;
+
+#[cfg(test)]
+fn t(rotation: Rotation, x: i32, y: i32) -> Tetramino {
+    let mut tetramino = Tetramino::new();
+    tetramino.set_shape(&TetraminoType::T);
+    while tetramino.rotation != rotation {
+        tetramino.rotate(RotationDirection::Clockwise);
+    }
+    tetramino.x = x;
+    tetramino.y = y;
+    tetramino
+}
+
+#[cfg(test)]
+#[test]
+fn test_three_corners_with_both_front_corners_is_full() {
+    let mut board = Board::new();
+    // T pointing down into a hole at (4, 0), centre at (4, 1).
+    for x in 0..COLS as i32 {
+        if x != 4 {
+            board.set(x, 0, 1);
+        }
+    }
+    board.set(3, 2, 1);
+    let tetramino = t(Rotation::Reverse, 3, -1);
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Clockwise, 0))), TSpin::Full);
+    assert_eq!(detect(&board, &tetramino, None), TSpin::None);
+    board.set(3, 2, 0);
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Clockwise, 0))), TSpin::None);
+}
+
+#[cfg(test)]
+#[test]
+fn test_missing_front_corner_is_mini_unless_last_kick() {
+    let mut board = Board::new();
+    // T pointing right on the floor against the left wall, the wall covers both corners
+    // behind it.
+    let tetramino = t(Rotation::Right, -1, -1);
+    assert_eq!(tetramino.cells().map(|(x, _)| x).min(), Some(0));
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Clockwise, 1))), TSpin::None);
+    board.set(1, 0, 1);
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Clockwise, 1))), TSpin::Mini);
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Clockwise, LAST_KICK))), TSpin::Full);
+}
+
+#[cfg(test)]
+#[test]
+fn test_only_t_spins() {
+    let mut board = Board::new();
+    for x in 0..COLS as i32 {
+        board.set(x, 0, 1);
+    }
+    board.set(3, 2, 1);
+    board.set(5, 2, 1);
+    let mut tetramino = t(Rotation::Spawn, 3, -1);
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Clockwise, 0))), TSpin::Full);
+    tetramino.tetramino_type = TetraminoType::L;
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Clockwise, 0))), TSpin::None);
+}
+
+#[cfg(test)]
+#[test]
+fn test_180_kicks_are_never_upgraded() {
+    let mut board = Board::new();
+    let tetramino = t(Rotation::Right, -1, -1);
+    board.set(1, 0, 1);
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Half, 1))), TSpin::Mini);
+    assert_eq!(detect(&board, &tetramino, Some((RotationDirection::Half, LAST_KICK))), TSpin::Mini);
+}
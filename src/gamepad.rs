@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::settings::Action;
+use crate::state::{check_state_change, GameState};
+
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(gamepad_connections);
+    }
+}
+
+/// One button, or one direction of an axis, of a gamepad.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum GamepadInput {
+    Button(GamepadButtonType),
+    /// The axis pushed past the deadzone towards its positive end, right or up.
+    Positive(GamepadAxisType),
+    /// The axis pushed past the deadzone towards its negative end, left or down.
+    Negative(GamepadAxisType),
+}
+
+/// Gamepad inputs bound to every action. Any of them triggers the action.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GamepadBindings {
+    /// How far a stick has to be pushed, from 0 to 1, before it counts as pressed.
+    pub deadzone: f32,
+    pub actions: BTreeMap<Action, Vec<GamepadInput>>,
+}
+
+impl GamepadBindings {
+    pub fn inputs(&self, action: Action) -> &[GamepadInput] {
+        self.actions.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: Action, gamepad: Gamepad, buttons: &Input<GamepadButton>, axes: &Axis<GamepadAxis>) -> bool {
+        self.inputs(action).iter().any(|input| match *input {
+            GamepadInput::Button(button) => buttons.pressed(GamepadButton(gamepad, button)),
+            GamepadInput::Positive(axis) => axes.get(GamepadAxis(gamepad, axis)).unwrap_or(0.0) > self.deadzone,
+            GamepadInput::Negative(axis) => axes.get(GamepadAxis(gamepad, axis)).unwrap_or(0.0) < -self.deadzone,
+        })
+    }
+
+    /// Only buttons are taken into account, axes have no notion of being just pressed.
+    pub fn just_pressed(&self, action: Action, gamepad: Gamepad, buttons: &Input<GamepadButton>) -> bool {
+        self.inputs(action).iter().any(|input| match *input {
+            GamepadInput::Button(button) => buttons.just_pressed(GamepadButton(gamepad, button)),
+            _ => false,
+        })
+    }
+
+    pub fn reset(&self, action: Action, gamepad: Gamepad, buttons: &mut Input<GamepadButton>) {
+        for input in self.inputs(action) {
+            if let GamepadInput::Button(button) = *input {
+                buttons.reset(GamepadButton(gamepad, button));
+            }
+        }
+    }
+}
+
+impl Default for GamepadBindings {
+    fn default() -> Self {
+        use GamepadAxisType::*;
+        use GamepadButtonType::*;
+        use GamepadInput::*;
+        Self {
+            deadzone: 0.5,
+            actions: BTreeMap::from([
+                (Action::MoveLeft, vec![Button(DPadLeft), Negative(DPadX), Negative(LeftStickX)]),
+                (Action::MoveRight, vec![Button(DPadRight), Positive(DPadX), Positive(LeftStickX)]),
+                (Action::RotateCW, vec![Button(East)]),
+                (Action::RotateCCW, vec![Button(South)]),
+                (Action::Rotate180, vec![Button(North)]),
+                (Action::SoftDrop, vec![Button(DPadDown), Negative(DPadY), Negative(LeftStickY)]),
+                (Action::HardDrop, vec![Button(DPadUp)]),
+                (Action::Hold, vec![Button(LeftTrigger), Button(RightTrigger)]),
+                (Action::Pause, vec![Button(Start)]),
+            ]),
+        }
+    }
+}
+
+/// Logs gamepads coming and going, and pauses a running game when one is unplugged so
+/// nobody loses a piece to a loose cable.
+fn gamepad_connections(mut events: EventReader<GamepadEvent>, mut state: ResMut<State<GameState>>) {
+    let mut disconnected = false;
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected => info!("gamepad {} connected", gamepad.0),
+            GamepadEventType::Disconnected => {
+                info!("gamepad {} disconnected", gamepad.0);
+                disconnected = true;
+            }
+            _ => {}
+        }
+    }
+    // Bevy takes one state change per frame, however many gamepads went away.
+    if disconnected && *state.current() == GameState::Playing {
+        check_state_change(state.push(GameState::Paused));
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_sticks_respect_the_deadzone() {
+    let bindings = GamepadBindings::default();
+    let gamepad = Gamepad(0);
+    let buttons = Input::<GamepadButton>::default();
+    let mut axes = Axis::<GamepadAxis>::default();
+    axes.set(GamepadAxis(gamepad, GamepadAxisType::LeftStickX), -0.3);
+    assert!(!bindings.pressed(Action::MoveLeft, gamepad, &buttons, &axes));
+    axes.set(GamepadAxis(gamepad, GamepadAxisType::LeftStickX), -0.8);
+    assert!(bindings.pressed(Action::MoveLeft, gamepad, &buttons, &axes));
+    assert!(!bindings.pressed(Action::MoveRight, gamepad, &buttons, &axes));
+    assert!(!bindings.pressed(Action::MoveLeft, Gamepad(1), &buttons, &axes));
+}
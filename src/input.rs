@@ -0,0 +1,53 @@
+use std::marker::PhantomData;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use crate::settings::{Action, Settings};
+
+/// Reads actions from the keyboard and from every connected gamepad, each through its own
+/// bindings. Systems that also check keys directly go through `keys`, as asking for
+/// `Input<KeyCode>` a second time would conflict.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    pub keys: ResMut<'w, Input<KeyCode>>,
+    buttons: ResMut<'w, Input<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    gamepads: Res<'w, Gamepads>,
+    settings: Res<'w, Settings>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ActionInput<'w, 's> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.settings.bindings.pressed(action, &self.keys)
+            || self.gamepads.iter().any(|&gamepad| {
+                self.settings.gamepad_bindings(gamepad).pressed(action, gamepad, &self.buttons, &self.axes)
+            })
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.settings.bindings.just_pressed(action, &self.keys)
+            || self.gamepads.iter().any(|&gamepad| {
+                self.settings.gamepad_bindings(gamepad).just_pressed(action, gamepad, &self.buttons)
+            })
+    }
+
+    /// Whether `button` went down on any gamepad, for menus that are not bound to actions.
+    pub fn gamepad_just_pressed(&self, button: GamepadButtonType) -> bool {
+        self.gamepads.iter().any(|&gamepad| self.buttons.just_pressed(GamepadButton(gamepad, button)))
+    }
+
+    pub fn reset_gamepad(&mut self, button: GamepadButtonType) {
+        for &gamepad in self.gamepads.iter() {
+            self.buttons.reset(GamepadButton(gamepad, button));
+        }
+    }
+
+    /// `KeyBindings::reset` for the keyboard and every gamepad.
+    pub fn reset(&mut self, action: Action) {
+        self.settings.bindings.reset(action, &mut self.keys);
+        for &gamepad in self.gamepads.iter() {
+            self.settings.gamepad_bindings(gamepad).reset(action, gamepad, &mut self.buttons);
+        }
+    }
+}
@@ -1,9 +1,23 @@
 use bevy::prelude::*;
+mod controls;
+mod gamepad;
+mod input;
+mod preview;
+mod records;
+mod settings;
+mod state;
 mod tilemap;
 mod tetramino;
-use tilemap::TilemapPlugin;
+use controls::ControlsPlugin;
+use gamepad::GamepadPlugin;
+use preview::PreviewPlugin;
+use records::Records;
+use settings::Settings;
+use state::GameStatePlugin;
+use tilemap::{BoardCamera, TilemapPlugin};
 use tetramino::TetraminoPlugin;
 use bevy_inspector_egui::WorldInspectorPlugin;
+use tetris_rs::engine::{GameConfig, MAX_COLS, MIN_COLS, MIN_ROWS};
 
 fn main() {
     let mut app = App::new();
@@ -13,9 +27,16 @@ fn main() {
         height: 600.0,
         ..Default::default()
     })
+    .insert_resource(game_config_from_args())
+    .insert_resource(Settings::load())
+    .insert_resource(Records::load())
     .add_plugins(DefaultPlugins)
+    .add_plugin(GameStatePlugin)
     .add_plugin(TilemapPlugin)
-    .add_plugin(TetraminoPlugin);
+    .add_plugin(TetraminoPlugin)
+    .add_plugin(PreviewPlugin)
+    .add_plugin(ControlsPlugin)
+    .add_plugin(GamepadPlugin);
 
     #[cfg(debug_assertions)]
     app.add_plugin(WorldInspectorPlugin::new());
@@ -25,5 +46,122 @@ fn main() {
 }
 
 fn camera_setup(mut commands: Commands) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d()).insert(BoardCamera);
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+
+/// Builds the game options from the command line, e.g. `--mode marathon --start-level 5 --lines none --time 180 --garbage messy --randomizer tgm --seed 42 --preview 3 --gravity nes --lock-delay 500 --das 133 --arr 0 --width 4`.
+fn game_config_from_args() -> GameConfig {
+    let mut config = GameConfig::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--mode" => match args.next().map(|value| value.parse()) {
+                Some(Ok(mode)) => config.mode = mode,
+                Some(Err(error)) => eprintln!("{}", error),
+                None => eprintln!("--mode needs a value"),
+            },
+            "--start-level" => match args.next().map(|value| value.parse()) {
+                Some(Ok(level)) if level > 0 => config.start_level = level,
+                Some(Ok(_)) => eprintln!("levels start at 1"),
+                Some(Err(error)) => eprintln!("invalid start level: {}", error),
+                None => eprintln!("--start-level needs a value"),
+            },
+            "--lines" => match args.next().map(|value| value.parse()) {
+                Some(Ok(line_goal)) => config.line_goal = line_goal,
+                Some(Err(error)) => eprintln!("{}", error),
+                None => eprintln!("--lines needs a value, a number of lines or none"),
+            },
+            "--time" => match args.next().map(|value| value.parse()) {
+                Some(Ok(seconds)) if seconds > 0 => config.time_limit = Some(seconds),
+                Some(Ok(_)) => eprintln!("the time limit must be positive"),
+                Some(Err(error)) => eprintln!("invalid time limit: {}", error),
+                None => eprintln!("--time needs a value in seconds"),
+            },
+            "--garbage" => match args.next().map(|value| value.parse()) {
+                Some(Ok(garbage)) => config.garbage = garbage,
+                Some(Err(error)) => eprintln!("{}", error),
+                None => eprintln!("--garbage needs a value"),
+            },
+            "--width" => match args.next().map(|value| value.parse()) {
+                Some(Ok(width)) if (MIN_COLS..=MAX_COLS).contains(&width) => config.width = width,
+                Some(Ok(_)) => eprintln!("the width must be from {} to {} cells", MIN_COLS, MAX_COLS),
+                Some(Err(error)) => eprintln!("invalid width: {}", error),
+                None => eprintln!("--width needs a value, from {} to {} cells", MIN_COLS, MAX_COLS),
+            },
+            "--height" => match args.next().map(|value| value.parse()) {
+                Some(Ok(height)) if height >= MIN_ROWS => config.height = height,
+                Some(Ok(_)) => eprintln!("the height must be at least {} cells", MIN_ROWS),
+                Some(Err(error)) => eprintln!("invalid height: {}", error),
+                None => eprintln!("--height needs a value, at least {} cells", MIN_ROWS),
+            },
+            "--randomizer" => match args.next().map(|value| value.parse()) {
+                Some(Ok(randomizer)) => config.randomizer = randomizer,
+                Some(Err(error)) => eprintln!("{}", error),
+                None => eprintln!("--randomizer needs a value"),
+            },
+            "--seed" => match args.next().map(|value| value.parse()) {
+                Some(Ok(seed)) => config.seed = Some(seed),
+                Some(Err(error)) => eprintln!("invalid seed: {}", error),
+                None => eprintln!("--seed needs a value"),
+            },
+            "--preview" => match args.next().map(|value| value.parse()) {
+                Some(Ok(preview_count)) => config.preview_count = preview_count,
+                Some(Err(error)) => eprintln!("invalid preview count: {}", error),
+                None => eprintln!("--preview needs a value"),
+            },
+            "--gravity" => match args.next().map(|value| value.parse()) {
+                Some(Ok(gravity)) => config.gravity = gravity,
+                Some(Err(error)) => eprintln!("{}", error),
+                None => eprintln!("--gravity needs a value"),
+            },
+            "--lock-delay" => match args.next().map(|value| seconds_from_millis(&value)) {
+                Some(Ok(lock_delay)) => config.lock_delay = lock_delay,
+                Some(Err(error)) => eprintln!("invalid lock delay: {}", error),
+                None => eprintln!("--lock-delay needs a value in milliseconds"),
+            },
+            "--lock-reset" => match args.next().map(|value| value.parse()) {
+                Some(Ok(lock_reset)) => config.lock_reset = lock_reset,
+                Some(Err(error)) => eprintln!("{}", error),
+                None => eprintln!("--lock-reset needs a value"),
+            },
+            "--das" => match args.next().map(|value| seconds_from_millis(&value)) {
+                Some(Ok(das)) => config.das = das,
+                Some(Err(error)) => eprintln!("invalid DAS: {}", error),
+                None => eprintln!("--das needs a value in milliseconds"),
+            },
+            "--arr" => match args.next().map(|value| seconds_from_millis(&value)) {
+                Some(Ok(arr)) => config.arr = arr,
+                Some(Err(error)) => eprintln!("invalid ARR: {}", error),
+                None => eprintln!("--arr needs a value in milliseconds"),
+            },
+            "--das-cut" => match args.next().map(|value| seconds_from_millis(&value)) {
+                Some(Ok(das_cut)) => config.das_cut = das_cut,
+                Some(Err(error)) => eprintln!("invalid DAS cut: {}", error),
+                None => eprintln!("--das-cut needs a value in milliseconds"),
+            },
+            "--soft-drop-factor" => match args.next().map(|value| value.parse::<f32>()) {
+                Some(Ok(factor)) if factor > 0.0 => config.soft_drop_factor = factor,
+                Some(Ok(_)) => eprintln!("the soft drop factor must be positive"),
+                Some(Err(error)) => eprintln!("invalid soft drop factor: {}", error),
+                None => eprintln!("--soft-drop-factor needs a value, `inf` drops instantly"),
+            },
+            "--line-clear-delay" => match args.next().map(|value| seconds_from_millis(&value)) {
+                Some(Ok(line_clear_delay)) => config.line_clear_delay = line_clear_delay,
+                Some(Err(error)) => eprintln!("invalid line clear delay: {}", error),
+                None => eprintln!("--line-clear-delay needs a value in milliseconds"),
+            },
+            "--are" => match args.next().map(|value| seconds_from_millis(&value)) {
+                Some(Ok(spawn_delay)) => config.spawn_delay = spawn_delay,
+                Some(Err(error)) => eprintln!("invalid ARE: {}", error),
+                None => eprintln!("--are needs a value in milliseconds"),
+            },
+            _ => eprintln!("unknown argument `{}`", arg),
+        }
+    }
+    config
+}
+
+fn seconds_from_millis(value: &str) -> Result<f32, std::num::ParseIntError> {
+    value.parse::<u32>().map(|milliseconds| milliseconds as f32 / 1000.0)
 }
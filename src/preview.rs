@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use tetris_rs::engine::{Game, Tetramino, TetraminoType};
+use crate::state::{GameState, Playfield};
+use crate::tetramino::spawn_tetramino_sprites;
+use crate::tilemap::*;
+
+/// Rows taken by every piece of the queue, two for the piece and one for the gap.
+const SLOT_HEIGHT: i32 = 3;
+
+pub struct PreviewPlugin;
+
+impl Plugin for PreviewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Playing)
+                .with_system(create_preview)
+                .with_system(create_hold))
+            .add_system(sync_preview.after("game step"))
+            .add_system(on_preview_changed.after(sync_preview))
+            .add_system(sync_hold.after("game step"))
+            .add_system(on_hold_changed.after(sync_hold));
+    }
+}
+
+/// One entry of the next queue, `index` counting from the piece that spawns next.
+#[derive(Component)]
+pub struct PreviewSlot {
+    pub index: usize,
+    pub tetramino_type: Option<TetraminoType>,
+}
+
+/// The held piece, drawn greyed out while holding is not allowed.
+#[derive(Component)]
+pub struct HoldSlot {
+    pub tetramino_type: Option<TetraminoType>,
+    pub can_hold: bool,
+}
+
+/// Spawns the black box behind a column of slots whose top left tile is `(left, rows - 1)`.
+fn spawn_slot_background(commands: &mut Commands, layout: &BoardLayout, left: i32, height: i32) {
+    // Tiles are centred on their coordinate, so the box spans half a tile around them.
+    let center = Vec2::new(left as f32 + 1.5, layout.rows as f32 - height as f32 / 2.0);
+    let tile_size = layout.tile_size;
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: tile_color(0),
+            custom_size: Some(Vec2::new(5.0 * tile_size, height as f32 * tile_size)),
+            ..default()
+        },
+        transform: Transform::from_translation(layout.get_coordinate(&0, &0) + center.extend(0.0) * tile_size),
+        ..Default::default()
+    })
+    .insert(Playfield);
+}
+
+fn create_preview(mut commands: Commands, game: Res<Game>, layout: Res<BoardLayout>) {
+    let count = game.queue.len() as i32;
+    let left = layout.cols + 1;
+    let top = layout.rows - 4;
+
+    spawn_slot_background(&mut commands, &layout, left, SLOT_HEIGHT * count);
+
+    for index in 0..count {
+        commands.spawn_bundle(TransformBundle::from_transform(Transform {
+            translation: layout.get_coordinate(&left, &(top - SLOT_HEIGHT * index)) + Vec3::new(0.0, 0.0, 0.1),
+            ..default()
+        }))
+        .insert(PreviewSlot { index: index as usize, tetramino_type: None })
+        .insert(Playfield);
+    }
+}
+
+fn sync_preview(game: Res<Game>, mut query: Query<&mut PreviewSlot>) {
+    if !game.is_changed() {
+        return;
+    }
+    for mut slot in query.iter_mut() {
+        let tetramino_type = game.queue.get(slot.index).copied();
+        if slot.tetramino_type != tetramino_type {
+            slot.tetramino_type = tetramino_type;
+        }
+    }
+}
+
+fn on_preview_changed(mut commands: Commands,
+                      layout: Res<BoardLayout>,
+                      query: Query<(Entity, &PreviewSlot), Changed<PreviewSlot>>) {
+    for (entity, slot) in query.iter() {
+        commands.entity(entity).despawn_descendants();
+        if let Some(tetramino_type) = slot.tetramino_type {
+            let mut tetramino = Tetramino::new();
+            tetramino.set_shape(&tetramino_type);
+            commands.entity(entity).with_children(|parent| {
+                let color = tile_color(tetramino_type.value());
+                spawn_tetramino_sprites(parent, &tetramino, layout.tile_size, |_| Some(color));
+            });
+        }
+    }
+}
+
+fn create_hold(mut commands: Commands, layout: Res<BoardLayout>) {
+    let left = -5;
+    spawn_slot_background(&mut commands, &layout, left, SLOT_HEIGHT);
+    commands.spawn_bundle(TransformBundle::from_transform(Transform {
+        translation: layout.get_coordinate(&left, &(layout.rows - 4)) + Vec3::new(0.0, 0.0, 0.1),
+        ..default()
+    }))
+    .insert(HoldSlot { tetramino_type: None, can_hold: true })
+    .insert(Playfield);
+}
+
+fn sync_hold(game: Res<Game>, mut query: Query<&mut HoldSlot>) {
+    if !game.is_changed() {
+        return;
+    }
+    for mut slot in query.iter_mut() {
+        if slot.tetramino_type != game.hold || slot.can_hold != game.can_hold {
+            slot.tetramino_type = game.hold;
+            slot.can_hold = game.can_hold;
+        }
+    }
+}
+
+fn on_hold_changed(mut commands: Commands,
+                   layout: Res<BoardLayout>,
+                   query: Query<(Entity, &HoldSlot), Changed<HoldSlot>>) {
+    for (entity, slot) in query.iter() {
+        commands.entity(entity).despawn_descendants();
+        if let Some(tetramino_type) = slot.tetramino_type {
+            let mut tetramino = Tetramino::new();
+            tetramino.set_shape(&tetramino_type);
+            let color = if slot.can_hold {
+                tile_color(tetramino_type.value())
+            } else {
+                Color::GRAY
+            };
+            commands.entity(entity).with_children(|parent| {
+                spawn_tetramino_sprites(parent, &tetramino, layout.tile_size, |_| Some(color));
+            });
+        }
+    }
+}
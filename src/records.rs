@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tetris_rs::engine::TetrisData;
+use crate::settings::{load_ron, save_ron};
+
+/// Where personal bests are read from at startup and written back after a new one.
+pub const RECORDS_PATH: &str = "records.ron";
+
+/// How many results every ultra leaderboard keeps.
+pub const LEADERBOARD_SIZE: usize = 10;
+
+/// One finished ultra game.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct UltraResult {
+    pub score: i32,
+    pub lines: u32,
+    pub tetrises: u32,
+    pub t_spins: u32,
+}
+
+impl From<&TetrisData> for UltraResult {
+    fn from(data: &TetrisData) -> Self {
+        Self {
+            score: data.score,
+            lines: data.lines,
+            tetrises: data.tetrises,
+            t_spins: data.t_spins,
+        }
+    }
+}
+
+/// Personal bests kept in `RECORDS_PATH`, read with `load_ron`.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Records {
+    /// Best sprint time for every line target played.
+    pub sprint: BTreeMap<u32, Duration>,
+    /// Best dig race time for every garbage line target played.
+    pub dig: BTreeMap<u32, Duration>,
+    /// Best ultra results for every time limit played in seconds, highest score first.
+    pub ultra: BTreeMap<u32, Vec<UltraResult>>,
+}
+
+impl Records {
+    pub fn load() -> Self {
+        load_ron(RECORDS_PATH)
+    }
+
+    pub fn save(&self) {
+        save_ron(RECORDS_PATH, self);
+    }
+
+    /// Keeps `time` as the best for a sprint of `lines` if it beats the one before.
+    /// Returns whether it did.
+    pub fn submit_sprint(&mut self, lines: u32, time: Duration) -> bool {
+        keep_best_time(&mut self.sprint, lines, time)
+    }
+
+    /// Keeps `time` as the best for a dig race of `lines` garbage lines if it beats the one
+    /// before. Returns whether it did.
+    pub fn submit_dig(&mut self, lines: u32, time: Duration) -> bool {
+        keep_best_time(&mut self.dig, lines, time)
+    }
+
+    /// Puts `result` on the leaderboard of ultra games of `seconds`, below the results
+    /// with the same score. Returns its rank counting from zero, `None` if it did not make
+    /// the leaderboard.
+    pub fn submit_ultra(&mut self, seconds: u32, result: UltraResult) -> Option<usize> {
+        let leaderboard = self.ultra.entry(seconds).or_default();
+        let rank = leaderboard.iter().position(|entry| entry.score < result.score).unwrap_or(leaderboard.len());
+        if rank >= LEADERBOARD_SIZE {
+            return None;
+        }
+        leaderboard.insert(rank, result);
+        leaderboard.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+}
+
+fn keep_best_time(bests: &mut BTreeMap<u32, Duration>, lines: u32, time: Duration) -> bool {
+    match bests.get(&lines) {
+        Some(&best) if best <= time => false,
+        _ => {
+            bests.insert(lines, time);
+            true
+        }
+    }
+}
+
+/// `time` as minutes, seconds and milliseconds, e.g. `1:05.032`.
+pub fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!("{}:{:02}.{:03}", millis / 60_000, millis / 1000 % 60, millis % 1000)
+}
+
+#[cfg(test)]
+#[test]
+fn test_sprint_bests_only_improve() {
+    let mut records = Records::default();
+    assert!(records.submit_sprint(40, Duration::from_secs(90)));
+    assert!(!records.submit_sprint(40, Duration::from_secs(95)));
+    assert!(records.submit_sprint(20, Duration::from_secs(95)));
+    assert!(records.submit_sprint(40, Duration::from_millis(65_032)));
+    assert_eq!(format_time(records.sprint[&40]), "1:05.032");
+    assert!(records.submit_dig(40, Duration::from_secs(120)));
+    assert_eq!(records.sprint[&40], Duration::from_millis(65_032));
+    let contents = ron::to_string(&records).unwrap();
+    assert_eq!(ron::from_str::<Records>(&contents).unwrap(), records);
+}
+
+#[cfg(test)]
+#[test]
+fn test_ultra_leaderboard_keeps_the_best_scores() {
+    let mut records = Records::default();
+    let result = |score| UltraResult { score, lines: 0, tetrises: 0, t_spins: 0 };
+    for score in 1..=LEADERBOARD_SIZE as i32 {
+        assert_eq!(records.submit_ultra(120, result(score * 100)), Some(0));
+    }
+    assert_eq!(records.submit_ultra(120, result(50)), None);
+    assert_eq!(records.submit_ultra(120, result(950)), Some(1));
+    assert_eq!(records.submit_ultra(120, result(1000)), Some(1));
+    assert_eq!(records.ultra[&120].len(), LEADERBOARD_SIZE);
+    assert_eq!(records.ultra[&120].last(), Some(&result(300)));
+    assert_eq!(records.submit_ultra(180, result(0)), Some(0));
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::gamepad::GamepadBindings;
+
+/// Where the settings are read from at startup and written back after rebinding.
+pub const SETTINGS_PATH: &str = "settings.ron";
+
+/// Something the player can do, independent of the keys bound to it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    RotateCW,
+    RotateCCW,
+    Rotate180,
+    SoftDrop,
+    HardDrop,
+    Hold,
+    Pause,
+    ToggleGhost,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::RotateCW,
+        Action::RotateCCW,
+        Action::Rotate180,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::Hold,
+        Action::Pause,
+        Action::ToggleGhost,
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::RotateCW => "Rotate clockwise",
+            Action::RotateCCW => "Rotate counterclockwise",
+            Action::Rotate180 => "Rotate 180",
+            Action::SoftDrop => "Soft drop",
+            Action::HardDrop => "Hard drop",
+            Action::Hold => "Hold",
+            Action::Pause => "Pause",
+            Action::ToggleGhost => "Toggle ghost",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Keys bound to every action. Any of them triggers the action.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct KeyBindings(pub BTreeMap<Action, Vec<KeyCode>>);
+
+impl KeyBindings {
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn pressed(&self, action: Action, keys: &Input<KeyCode>) -> bool {
+        keys.any_pressed(self.keys(action).iter().copied())
+    }
+
+    pub fn just_pressed(&self, action: Action, keys: &Input<KeyCode>) -> bool {
+        keys.any_just_pressed(self.keys(action).iter().copied())
+    }
+
+    /// Forgets the presses of every key bound to `action`, so the next state does not see
+    /// them again this frame.
+    pub fn reset(&self, action: Action, keys: &mut Input<KeyCode>) {
+        for &key in self.keys(action) {
+            keys.reset(key);
+        }
+    }
+
+    /// Adds `key` to `action`, unbinding it from any other action first.
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        for bound in self.0.values_mut() {
+            bound.retain(|&bound_key| bound_key != key);
+        }
+        self.0.entry(action).or_default().push(key);
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.0.remove(&action);
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(BTreeMap::from([
+            (Action::MoveLeft, vec![KeyCode::J, KeyCode::Left]),
+            (Action::MoveRight, vec![KeyCode::K, KeyCode::Right]),
+            (Action::RotateCW, vec![KeyCode::F, KeyCode::Up]),
+            (Action::RotateCCW, vec![KeyCode::D]),
+            (Action::Rotate180, vec![KeyCode::S]),
+            (Action::SoftDrop, vec![KeyCode::L, KeyCode::Down]),
+            (Action::HardDrop, vec![KeyCode::Space]),
+            (Action::Hold, vec![KeyCode::A]),
+            (Action::Pause, vec![KeyCode::Escape, KeyCode::P]),
+            (Action::ToggleGhost, vec![KeyCode::G]),
+        ]))
+    }
+}
+
+/// Player preferences kept in `SETTINGS_PATH`, read with `load_ron`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub bindings: KeyBindings,
+    /// Bindings for gamepads without a profile of their own.
+    pub gamepad: GamepadBindings,
+    /// Profiles for specific gamepads, keyed by the id Bevy gives them in plug order.
+    pub gamepads: BTreeMap<usize, GamepadBindings>,
+    /// Pixels per cell. The board is drawn smaller when it does not fit the window.
+    pub tile_size: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            bindings: KeyBindings::default(),
+            gamepad: GamepadBindings::default(),
+            gamepads: BTreeMap::new(),
+            tile_size: 20.0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn gamepad_bindings(&self, gamepad: Gamepad) -> &GamepadBindings {
+        self.gamepads.get(&gamepad.0).unwrap_or(&self.gamepad)
+    }
+
+    /// Reads `SETTINGS_PATH`, see `load_ron`.
+    pub fn load() -> Self {
+        load_ron(SETTINGS_PATH)
+    }
+
+    pub fn save(&self) {
+        save_ron(SETTINGS_PATH, self);
+    }
+}
+
+/// Reads a RON file, falling back to the default value when it is missing or broken.
+/// Types marked `#[serde(default)]` also take the default of every field it lacks.
+pub fn load_ron<T: DeserializeOwned + Default>(path: &str) -> T {
+    match fs::read_to_string(path) {
+        Ok(contents) => ron::from_str(&contents).unwrap_or_else(|error| {
+            eprintln!("ignoring {}: {}", path, error);
+            T::default()
+        }),
+        Err(_) => T::default(),
+    }
+}
+
+pub fn save_ron<T: Serialize>(path: &str, value: &T) {
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .expect("settings and records are always serializable");
+    if let Err(error) = fs::write(path, contents) {
+        eprintln!("could not save {}: {}", path, error);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_default_bindings_cover_every_action() {
+    let bindings = KeyBindings::default();
+    for action in Action::ALL {
+        assert!(!bindings.keys(action).is_empty(), "{} is not bound", action);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_bind_moves_a_key_between_actions() {
+    let mut bindings = KeyBindings::default();
+    bindings.bind(Action::Hold, KeyCode::Space);
+    assert_eq!(bindings.keys(Action::Hold), [KeyCode::A, KeyCode::Space]);
+    assert!(bindings.keys(Action::HardDrop).is_empty());
+    bindings.bind(Action::Hold, KeyCode::G);
+    assert!(bindings.keys(Action::ToggleGhost).is_empty());
+    bindings.clear(Action::Hold);
+    assert!(bindings.keys(Action::Hold).is_empty());
+}
+
+#[cfg(test)]
+#[test]
+fn test_settings_round_trip_through_ron() {
+    let mut settings = Settings::default();
+    settings.bindings.bind(Action::Rotate180, KeyCode::W);
+    let contents = ron::to_string(&settings).unwrap();
+    assert_eq!(ron::from_str::<Settings>(&contents).unwrap(), settings);
+    let partial: Settings = ron::from_str("(bindings: ({Hold: [LShift]}))").unwrap();
+    assert_eq!(partial.bindings.keys(Action::Hold), [KeyCode::LShift]);
+    assert_eq!(partial.tile_size, Settings::default().tile_size);
+}
@@ -0,0 +1,322 @@
+use bevy::ecs::schedule::StateError;
+use bevy::prelude::*;
+use tetris_rs::engine::{Game, GameConfig, GameMode, GameOverReason, GarbageStyle, LineGoal, MARATHON_MAX_LEVEL};
+use crate::input::ActionInput;
+use crate::records::{format_time, Records, UltraResult};
+use crate::settings::{Action, Settings};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameState {
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+    Controls,
+}
+
+/// Everything drawn for a running game: tiles, the active piece and the score. Spawned
+/// on entering `Playing` from the menu or the game over screen, despawned on leaving the
+/// game over screen and on coming back to the menu. Pausing keeps it.
+#[derive(Component)]
+pub struct Playfield;
+
+#[derive(Component)]
+struct MenuScreen;
+
+#[derive(Component)]
+struct PauseScreen;
+
+#[derive(Component)]
+struct GameOverScreen;
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state(GameState::Menu)
+            .add_system_set(SystemSet::on_enter(GameState::Menu)
+                .with_system(despawn_with::<Playfield>)
+                .with_system(create_menu_screen))
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(menu_input))
+            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(despawn_with::<MenuScreen>))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(playing_input.after("game step")))
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(create_pause_screen))
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(paused_input))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(despawn_with::<PauseScreen>))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(create_game_over_screen))
+            .add_system_set(SystemSet::on_update(GameState::GameOver).with_system(game_over_input))
+            .add_system_set(SystemSet::on_exit(GameState::GameOver)
+                .with_system(despawn_with::<GameOverScreen>)
+                .with_system(despawn_with::<Playfield>));
+    }
+}
+
+pub fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn screen_text(asset_server: &AssetServer, value: String) -> TextBundle {
+    TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(40.0),
+                left: Val::Px(20.0),
+                ..default()
+            },
+            ..default()
+        },
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: asset_server.load("font.otf"),
+                font_size: 30.0,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+fn menu_text(game: &Game, records: &Records) -> String {
+    let mode = match (game.config.mode, game.line_goal()) {
+        (GameMode::Sprint, Some(lines)) => match records.sprint.get(&lines) {
+            Some(&best) => format!("Sprint, {} lines, best {}", lines, format_time(best)),
+            None => format!("Sprint, {} lines", lines),
+        },
+        (GameMode::Ultra, _) => {
+            let seconds = game.time_limit().unwrap_or_default().as_secs() as u32;
+            match records.ultra.get(&seconds).and_then(|leaderboard| leaderboard.first()) {
+                Some(best) => format!("Ultra, {} seconds, best {}", seconds, best.score),
+                None => format!("Ultra, {} seconds", seconds),
+            }
+        }
+        (GameMode::Dig, Some(lines)) => {
+            let style = game.config.garbage;
+            match records.dig.get(&lines) {
+                Some(&best) => format!("Dig, {} garbage lines, {}, best {}", lines, style, format_time(best)),
+                None => format!("Dig, {} garbage lines, {}", lines, style),
+            }
+        }
+        (GameMode::Marathon, Some(lines)) => {
+            format!("Marathon, {} lines, start level {}", lines, game.config.start_level)
+        }
+        (GameMode::Marathon, None) => format!("Marathon, no line goal, start level {}", game.config.start_level),
+        _ => format!("Endless, start level {}", game.config.start_level),
+    };
+    let mut lines = vec![
+        "Tetris".to_string(),
+        format!("Mode: {}", mode),
+        "Press Enter or Start to start".to_string(),
+        "Press M to change mode".to_string(),
+    ];
+    if game.config.mode.has_start_level() {
+        lines.push("Press Left or Right to change the start level".to_string());
+    }
+    if game.config.mode.line_goals().len() > 1 {
+        lines.push("Press L to change the line goal".to_string());
+    }
+    if game.config.mode == GameMode::Dig {
+        lines.push("Press G to change the garbage".to_string());
+    }
+    lines.push("Press C to change controls".to_string());
+    lines.join("\n")
+}
+
+fn create_menu_screen(mut commands: Commands, asset_server: Res<AssetServer>, game: Res<Game>, records: Res<Records>) {
+    commands
+        .spawn_bundle(screen_text(&asset_server, menu_text(&game, &records)))
+        .insert(MenuScreen);
+}
+
+fn create_pause_screen(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    let keys: Vec<String> = settings.bindings.keys(Action::Pause).iter().map(|key| format!("{:?}", key)).collect();
+    commands
+        .spawn_bundle(screen_text(&asset_server, format!(
+            "Paused\nPress {} to resume\nPress Q to quit to menu",
+            keys.join(" or ")
+        )))
+        .insert(PauseScreen);
+}
+
+/// Shows the final score, or the results of a finished sprint, dig or ultra game, which
+/// also go into the records.
+fn create_game_over_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    mut records: ResMut<Records>,
+) {
+    let result = match (game.game_over, game.config.mode) {
+        (Some(GameOverReason::Finished), GameMode::Sprint | GameMode::Dig) => race_result(&game, &mut records),
+        (Some(GameOverReason::Finished), GameMode::Ultra) => ultra_result(&game, &mut records),
+        (Some(GameOverReason::Finished), _) => summary("Marathon complete", &game),
+        _ => summary("Game over", &game),
+    };
+    let mut text = screen_text(&asset_server, format!(
+        "{}\nPress R or Start to restart\nPress Esc or Select to quit to menu",
+        result
+    ));
+    text.text.sections[0].style.font_size = 24.0;
+    commands.spawn_bundle(text).insert(GameOverScreen);
+}
+
+/// Score, level and lines of a game played for score, with what its clears were.
+fn summary(title: &str, game: &Game) -> String {
+    let data = game.data;
+    format!(
+        "{}\nScore: {}\nLevel: {}\nLines: {}\n\
+         Singles: {}, doubles: {}, triples: {}, tetrises: {}\nT-spins: {}, perfect clears: {}",
+        title,
+        data.score,
+        data.level,
+        data.lines,
+        data.singles,
+        data.doubles,
+        data.triples,
+        data.tetrises,
+        data.t_spins,
+        data.perfect_clears
+    )
+}
+
+/// Time and pace of a finished sprint or dig race.
+fn race_result(game: &Game, records: &mut Records) -> String {
+    let data = game.data;
+    let lines = game.line_goal().unwrap_or_default();
+    let (title, new_best) = match game.config.mode {
+        GameMode::Dig => ("Dig finished", records.submit_dig(lines, data.time)),
+        _ => ("Sprint finished", records.submit_sprint(lines, data.time)),
+    };
+    let best = if new_best {
+        records.save();
+        "New personal best!".to_string()
+    } else {
+        let bests = if game.config.mode == GameMode::Dig { &records.dig } else { &records.sprint };
+        format!("Personal best: {}", format_time(bests[&lines]))
+    };
+    format!(
+        "{}\nTime: {}\n{}\nPieces: {}\nPieces per second: {:.2}\nKeys per piece: {:.2}",
+        title,
+        format_time(data.time),
+        best,
+        data.pieces,
+        data.pieces_per_second(),
+        data.keys_per_piece()
+    )
+}
+
+fn ultra_result(game: &Game, records: &mut Records) -> String {
+    let data = game.data;
+    let seconds = game.time_limit().unwrap_or_default().as_secs() as u32;
+    let rank = records.submit_ultra(seconds, UltraResult::from(&data));
+    if rank.is_some() {
+        records.save();
+    }
+    let mut lines = vec![
+        "Time's up".to_string(),
+        format!("Score: {}", data.score),
+        format!("Lines: {}, tetrises: {}, T-spins: {}", data.lines, data.tetrises, data.t_spins),
+        String::new(),
+        format!("Leaderboard, {} seconds", seconds),
+    ];
+    for (index, entry) in records.ultra[&seconds].iter().enumerate().take(5) {
+        let marker = if rank == Some(index) { ">" } else { " " };
+        lines.push(format!(
+            "{}{}. {}  {} lines  {} tetrises  {} T-spins",
+            marker, index + 1, entry.score, entry.lines, entry.tetrises, entry.t_spins
+        ));
+    }
+    lines.join("\n")
+}
+
+fn menu_input(
+    mut input: ActionInput,
+    mut game: ResMut<Game>,
+    records: Res<Records>,
+    mut text: Query<&mut Text, With<MenuScreen>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if input.keys.just_pressed(KeyCode::Return) || input.gamepad_just_pressed(GamepadButtonType::Start) {
+        input.keys.reset(KeyCode::Return);
+        input.reset_gamepad(GamepadButtonType::Start);
+        check_state_change(state.set(GameState::Playing));
+    } else if input.keys.just_pressed(KeyCode::C) {
+        input.keys.reset(KeyCode::C);
+        check_state_change(state.set(GameState::Controls));
+    } else if change_mode_options(&input.keys, &mut game.config) {
+        for mut text in text.iter_mut() {
+            text.sections[0].value = menu_text(&game, &records);
+        }
+    }
+}
+
+/// Menu keys picking the mode and its options. They take effect when the next game
+/// starts, `Game::restart` keeps the config. Returns whether anything changed.
+fn change_mode_options(keys: &Input<KeyCode>, config: &mut GameConfig) -> bool {
+    if keys.just_pressed(KeyCode::M) {
+        config.mode = config.mode.next();
+        // Line goals and start levels of one mode make no sense in the next.
+        config.line_goal = LineGoal::Default;
+        config.start_level = 1;
+    } else if keys.just_pressed(KeyCode::Left) && config.mode.has_start_level() {
+        config.start_level = config.start_level.max(2) - 1;
+    } else if keys.just_pressed(KeyCode::Right) && config.mode.has_start_level() {
+        config.start_level = (config.start_level + 1).min(MARATHON_MAX_LEVEL);
+    } else if keys.just_pressed(KeyCode::L) && config.mode.line_goals().len() > 1 {
+        let goals = config.mode.line_goals();
+        let current = if config.line_goal == LineGoal::Default { goals[0] } else { config.line_goal };
+        let index = goals.iter().position(|&goal| goal == current).map_or(0, |index| index + 1);
+        config.line_goal = goals[index % goals.len()];
+    } else if keys.just_pressed(KeyCode::G) && config.mode == GameMode::Dig {
+        config.garbage = match config.garbage {
+            GarbageStyle::Clean => GarbageStyle::Messy,
+            GarbageStyle::Messy => GarbageStyle::Clean,
+        };
+    } else {
+        return false;
+    }
+    true
+}
+
+/// Logs a state change that failed, unless another change was queued first this frame, as
+/// `gamepad_connections` may do. The first one wins and the game picks up from there.
+pub fn check_state_change(result: Result<(), StateError>) {
+    match result {
+        Ok(()) | Err(StateError::StateAlreadyQueued) => {}
+        Err(error) => warn!("state change failed: {}", error),
+    }
+}
+
+fn playing_input(mut input: ActionInput, game: Res<Game>, mut state: ResMut<State<GameState>>) {
+    if game.is_over() {
+        check_state_change(state.set(GameState::GameOver));
+    } else if input.just_pressed(Action::Pause) {
+        input.reset(Action::Pause);
+        check_state_change(state.push(GameState::Paused));
+    }
+}
+
+fn paused_input(mut input: ActionInput, mut state: ResMut<State<GameState>>) {
+    if input.just_pressed(Action::Pause) {
+        input.reset(Action::Pause);
+        check_state_change(state.pop());
+    } else if input.keys.just_pressed(KeyCode::Q) || input.gamepad_just_pressed(GamepadButtonType::Select) {
+        input.keys.reset(KeyCode::Q);
+        check_state_change(state.replace(GameState::Menu));
+    }
+}
+
+fn game_over_input(mut input: ActionInput, mut state: ResMut<State<GameState>>) {
+    if input.keys.just_pressed(KeyCode::R) || input.gamepad_just_pressed(GamepadButtonType::Start) {
+        input.keys.reset(KeyCode::R);
+        input.reset_gamepad(GamepadButtonType::Start);
+        check_state_change(state.set(GameState::Playing));
+    } else if input.keys.just_pressed(KeyCode::Escape) || input.gamepad_just_pressed(GamepadButtonType::Select) {
+        input.keys.reset(KeyCode::Escape);
+        check_state_change(state.set(GameState::Menu));
+    }
+}
@@ -1,503 +1,177 @@
-use bevy::{prelude::*, core::FixedTimestep};
+use bevy::prelude::*;
+use tetris_rs::engine::{Game, GameConfig, Phase, PlayerInput, Tetramino};
 use crate::tilemap::*;
-use rand::Rng;
-
-const I_TETRAMINO: [[u8; 4]; 4] = [
-    [0, 0, 0, 0],
-    [1, 1, 1, 1],
-    [0, 0, 0, 0],
-    [0, 0, 0, 0],
-];
-
-const S_TETRAMINO: [[u8; 4]; 4] = [
-    [0, 0, 0, 0],
-    [0, 1, 1, 0],
-    [1, 1, 0, 0],
-    [0, 0, 0, 0],
-];
-
-const Z_TETRAMINO: [[u8; 4]; 4] = [
-    [0, 0, 0, 0],
-    [1, 1, 0, 0],
-    [0, 1, 1, 0],
-    [0, 0, 0, 0],
-];
-
-const J_TETRAMINO: [[u8; 4]; 4] = [
-    [0, 0, 0, 0],
-    [1, 0, 0, 0],
-    [1, 1, 1, 0],
-    [0, 0, 0, 0],
-];
-
-const L_TETRAMINO: [[u8; 4]; 4] = [
-    [0, 0, 0, 0],
-    [1, 1, 1, 0],
-    [1, 0, 0, 0],
-    [0, 0, 0, 0],
-];
-
-const O_TETRAMINO: [[u8; 4]; 4] = [
-    [0, 0, 0, 0],
-    [0, 1, 1, 0],
-    [0, 1, 1, 0],
-    [0, 0, 0, 0],
-];
-
-const T_TETRAMINO: [[u8; 4]; 4] = [
-    [0, 0, 0, 0],
-    [0, 1, 0, 0],
-    [1, 1, 1, 0],
-    [0, 0, 0, 0],
-];
-
-#[derive(Clone, Copy)]
-pub enum TetraminoType {
-    I,
-    S,
-    Z,
-    J,
-    L,
-    O,
-    T,
-}
+use crate::input::ActionInput;
+use crate::settings::Action;
+use crate::state::{GameState, Playfield};
 
 pub struct TetraminoPlugin;
 
 impl Plugin for TetraminoPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(create_random_tetramino_system)
-            .add_event::<CollidedEvent>()
-            .add_system(on_tetramino_changed)
-            .add_system(keyboard_input);
-        
-        app.add_stage("Tetramino fall", 
-            SystemStage::parallel()
-                    .with_run_criteria(FixedTimestep::step(0.5).with_label("fall"))
-                    .with_system(fall_system)
-        );
-
-    }
-}
-
-
-#[derive(Component, Clone, Copy)]
-pub struct Tetramino {
-    pub x: i32,
-    pub y: i32,
-    pub shape: [[u8; 4]; 4],
-    pub tetramino_type: TetraminoType,
-}
-
-struct CollidedEvent;
-
-impl Tetramino {
-    pub fn new() -> Self {
-        Self { 
-            x: COLS as i32 / 2 - 2, 
-            y: ROWS as i32 - 4, 
-            shape: [
-                [0, 0, 0, 0],
-                [0, 0, 0, 0],
-                [0, 0, 0, 0],
-                [0, 0, 0, 0],
-            ],
-            tetramino_type: TetraminoType::I,
-        }
-    }
-
-    pub fn rotate_clockwise(&mut self) {
-        let mut new_shape = [[0; 4]; 4];
-        for x in 0..4 {
-            for y in 0..4 {
-                new_shape[y][3 - x] = self.shape[x][y];
-            }
-        }
-        self.shape = new_shape;
-    }
-
-    pub fn rotate_conterclockwise(&mut self) {
-        let mut new_shape = [[0; 4]; 4];
-        for x in 0..4 {
-            for y in 0..4 {
-                new_shape[3 - y][x] = self.shape[x][y];
-            }
-        }
-        self.shape = new_shape;
-    }
-
-    pub fn get_bounds(&self) -> (i32, i32, i32, i32) {
-        let mut min_x: i32 = 3;
-        let mut min_y: i32 = 3;
-        let mut max_x: i32 = 0;
-        let mut max_y: i32 = 0;
-        for x in 0..4 {
-            for y in 0..4 {
-                if self.shape[x as usize][y as usize] == 1 {
-                    if x < min_x {
-                        min_x = x;
-                    }
-                    if x > max_x {
-                        max_x = x;
-                    }
-                    if y < min_y {
-                        min_y = y;
-                    }
-                    if y > max_y {
-                        max_y = y;
-                    }
-                }
-            }
-        }
-        (min_x, min_y, max_x, max_y)
+        let config = app.world.get_resource::<GameConfig>().cloned().unwrap_or_default();
+        app.insert_resource(Game::with_config(config))
+            .insert_resource(ShowGhost(true))
+            .add_system_set(SystemSet::on_enter(GameState::Playing)
+                .with_system(start_game)
+                .with_system(create_tetramino_system))
+            .add_system_set(SystemSet::on_update(GameState::Playing)
+                .with_system(read_input.label("game step"))
+                .with_system(toggle_ghost))
+            .add_system(sync_tetramino.after("game step"))
+            .add_system(on_tetramino_changed.after(sync_tetramino))
+            .add_system(sync_ghost.after("game step"))
+            .add_system(on_ghost_changed.after(sync_ghost));
     }
-
-    pub fn set_shape(&mut self, tetramino_type: &TetraminoType) {
-        match tetramino_type {
-            TetraminoType::I => self.shape = I_TETRAMINO,
-            TetraminoType::S => self.shape = S_TETRAMINO,
-            TetraminoType::Z => self.shape = Z_TETRAMINO,
-            TetraminoType::J => self.shape = J_TETRAMINO,
-            TetraminoType::L => self.shape = L_TETRAMINO,
-            TetraminoType::O => self.shape = O_TETRAMINO,
-            TetraminoType::T => self.shape = T_TETRAMINO,
-        }
-        self.tetramino_type = *tetramino_type;
-    }
-}
-
-pub fn create_random_tetramino_system(mut commands: Commands) {
-    create_random_tetramino(&mut commands);
 }
 
-pub fn create_random_tetramino(commands: &mut Commands) {
-    let mut rng = rand::thread_rng();
-    let tetramino_type = match rng.gen_range(0..7) {
-        0 => TetraminoType::I,
-        1 => TetraminoType::S,
-        2 => TetraminoType::Z,
-        3 => TetraminoType::J,
-        4 => TetraminoType::L,
-        5 => TetraminoType::O,
-        6 => TetraminoType::T,
-        _ => unreachable!(),
-    };
+/// The piece currently controlled by the player, as last drawn. Copied from `Game` only
+/// when it differs, so `Changed<ActiveTetramino>` fires on moves and rotations only.
+/// `None` between pieces, while lines clear and during ARE.
+#[derive(Component)]
+pub struct ActiveTetramino(pub Option<Tetramino>);
 
-    let mut tetramino = Tetramino::new();
-    tetramino.set_shape(&tetramino_type);
+/// Where the active piece would land on a hard drop, `None` while it is hidden.
+#[derive(Component)]
+pub struct GhostTetramino(pub Option<Tetramino>);
 
-    let color = match &tetramino_type {
-        TetraminoType::I => Color::rgb(0.0, 0.0, 1.0),
-        TetraminoType::S => Color::rgb(1.0, 0.0, 0.0),
-        TetraminoType::Z => Color::rgb(0.0, 1.0, 0.0),
-        TetraminoType::J => Color::rgb(0.0, 1.0, 1.0),
-        TetraminoType::L => Color::rgb(1.0, 1.0, 0.0),
-        TetraminoType::O => Color::rgb(1.0, 0.5, 0.0),
-        TetraminoType::T => Color::rgb(0.5, 0.0, 1.0),
-    };
+/// Whether the ghost piece is drawn. Toggled by `Action::ToggleGhost` while playing.
+pub struct ShowGhost(pub bool);
 
+pub fn create_tetramino_system(mut commands: Commands, game: Res<Game>, layout: Res<BoardLayout>) {
     commands.spawn_bundle(
         TransformBundle::from_transform(Transform {
-            translation: get_coordinate(&tetramino.x, &tetramino.y) + Vec3::new(0.0, 0.0, 0.1),
+            translation: layout.get_coordinate(&game.active.x, &game.active.y) + Vec3::new(0.0, 0.0, 0.1),
             ..default()
         })
         )
-        .with_children(|parent| {
-            for x in 0..4 {
-                for y in 0..4 {
-                    if tetramino.shape[x][y] == 1 {
-                        parent.spawn_bundle(SpriteBundle {
-                            sprite: Sprite {
-                                color,
-                                custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(Vec3::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE, 0.0)),
-                            ..Default::default()
-                        });
-                    }
-                }
-            }
-        })
-        .insert(tetramino);
+        .insert(ActiveTetramino(Some(game.active)))
+        .insert(Playfield);
+    commands.spawn_bundle(TransformBundle::default())
+        .insert(GhostTetramino(None))
+        .insert(Playfield);
+}
 
+/// Entering `Playing` from the menu or the game over screen always starts a new game.
+/// Resuming from pause does not go through here.
+fn start_game(mut game: ResMut<Game>) {
+    game.restart();
 }
 
-fn on_tetramino_changed(mut commands: Commands, 
+fn sync_tetramino(game: Res<Game>, mut query: Query<&mut ActiveTetramino>) {
+    let tetramino = if game.phase == Phase::Falling {
+        Some(game.active)
+    } else {
+        None
+    };
+    for mut active in query.iter_mut() {
+        if active.0 != tetramino {
+            active.0 = tetramino;
+        }
+    }
+}
+
+fn on_tetramino_changed(mut commands: Commands,
+                        layout: Res<BoardLayout>,
                         q_children: Query<(Entity, &Parent, &Sprite)>,
-                        mut q_parent: Query<(Entity, &Tetramino, &mut Transform), Changed<Tetramino>>) {
-    for (tetramino_entity, tetramino, mut transform) in q_parent.iter_mut() {
+                        mut q_parent: Query<(Entity, &ActiveTetramino, &mut Transform), Changed<ActiveTetramino>>) {
+    for (tetramino_entity, active, mut transform) in q_parent.iter_mut() {
         for (entity, parent, _sprite) in q_children.iter() {
             if parent.0 != tetramino_entity {
                 continue;
             }
             commands.entity(entity).despawn();
         }
-
-        transform.translation = get_coordinate(&tetramino.x, &tetramino.y) + Vec3::new(0.0, 0.0, 0.1);
-
-        let color = match &tetramino.tetramino_type {
-            TetraminoType::I => Color::rgb(0.0, 0.0, 1.0),
-            TetraminoType::S => Color::rgb(1.0, 0.0, 0.0),
-            TetraminoType::Z => Color::rgb(0.0, 1.0, 0.0),
-            TetraminoType::J => Color::rgb(0.0, 1.0, 1.0),
-            TetraminoType::L => Color::rgb(1.0, 1.0, 0.0),
-            TetraminoType::O => Color::rgb(1.0, 0.5, 0.0),
-            TetraminoType::T => Color::rgb(0.5, 0.0, 1.0),
+        let tetramino = match &active.0 {
+            Some(tetramino) => tetramino,
+            None => continue,
         };
 
+        transform.translation = layout.get_coordinate(&tetramino.x, &tetramino.y) + Vec3::new(0.0, 0.0, 0.1);
+
         commands.entity(tetramino_entity).with_children(|parent| {
-            for x in 0..4 {
-                for y in 0..4 {
-                    if tetramino.shape[x][y] == 1 {
-                        parent.spawn_bundle(SpriteBundle {
-                            sprite: Sprite {
-                                color,
-                                custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(Vec3::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE, 0.0)),
-                            ..Default::default()
-                        });
-                    }
-                }
-            }
+            let color = tile_color(tetramino.tetramino_type.value());
+            spawn_tetramino_sprites(parent, tetramino, layout.tile_size, |y| layout.cell_color(tetramino.y + y, color));
         });
     }
 }
 
-fn keyboard_input(
-    keys: Res<Input<KeyCode>>,
-    mut query: Query<(Entity, &mut Tetramino)>,
-    tiles_query: Query<(Entity, &mut Tile)>,
-) {
-    let is_collided_left = check_is_collided(&query,&tiles_query, (-1, 0), 0);
-    let is_collided_right = check_is_collided(&query,&tiles_query, (1, 0), 0);
-    let is_collided_clockwise = check_is_collided(&query,&tiles_query, (0, 0), 1);
-    let is_collided_conterclockwise = check_is_collided(&query,&tiles_query, (0, 0), -1);
-    if keys.just_pressed(KeyCode::D) {
-        for (_, mut tetramino) in query.iter_mut() {
-            if !is_collided_conterclockwise {
-                tetramino.rotate_conterclockwise();
-            }
-        }
-    } else if keys.just_pressed(KeyCode::F) {
-        for (_, mut tetramino) in query.iter_mut() {
-            if !is_collided_clockwise {
-                tetramino.rotate_clockwise();
-            }
-        }
-    }
-    if keys.just_pressed(KeyCode::J) {
-        for (_, mut tetramino) in query.iter_mut() {
-            let (min_x, _, _, _) = tetramino.get_bounds();
-            if tetramino.x > -min_x && !is_collided_left {
-                tetramino.x -= 1;
-            }
-        }
-    } else if keys.just_pressed(KeyCode::K) {
-        for (_, mut tetramino) in query.iter_mut() {
-            let (_, _, max_x, _) = tetramino.get_bounds();
-            if tetramino.x < COLS as i32 - max_x - 1 && !is_collided_right {
-                tetramino.x += 1;
-            }
-        }
+fn toggle_ghost(input: ActionInput, mut show_ghost: ResMut<ShowGhost>) {
+    if input.just_pressed(Action::ToggleGhost) {
+        show_ghost.0 = !show_ghost.0;
     }
-
 }
 
-fn check_is_collided(tetramino_query: &Query<(Entity, &mut Tetramino)>,
-                     field_query: &Query<(Entity, &mut Tile)>,
-                     delta: (i32, i32),
-                     rotation: i32) -> bool {
-    for (_, tetramino) in tetramino_query.iter() {
-        let (min_x, min_y, max_x, max_y) = tetramino.get_bounds();
-        if delta.1 == -1 && tetramino.y + min_y == 0 {
-            return true;
-        }
-        for x in min_x..max_x + 1 {
-            for y in min_y..max_y + 1 {
-                if tetramino.shape[x as usize][y as usize] == 1 {
-                    match rotation {
-                        0 => {
-                            let tile_x = x + tetramino.x + delta.0;
-                            let tile_y = y + tetramino.y + delta.1;
-                            for (_entity, tile) in field_query.iter() {
-                                if tile.x == tile_x && tile.y == tile_y && tile.value > 0 {
-                                    return true;
-                                }
-                            }
-                        },
-                        -1 => {
-                            let mut tetramino_cloned = tetramino.clone();
-                            tetramino_cloned.rotate_conterclockwise();
-                            let (min_x, min_y, max_x, max_y) = tetramino_cloned.get_bounds();
-                            if tetramino_cloned.y + min_y <= 0 {
-                                return true;
-                            }
-                            if tetramino_cloned.y + max_y >= ROWS as i32 - 1 {
-                                return true;
-                            }
-                            if tetramino_cloned.x + min_x <= 0 {
-                                return true;
-                            }
-                            if tetramino_cloned.x + max_x >= COLS as i32 - 1 {
-                                return true;
-                            }
-                            let tile_x = x + tetramino_cloned.x;
-                            let tile_y = y + tetramino_cloned.y;
-                            for (_entity, tile) in field_query.iter() {
-                                if tile.x == tile_x && tile.y == tile_y && tile.value > 0 {
-                                    return true;
-                                }
-                            }
-                        },
-                        1 => {
-                            let mut tetramino_cloned = tetramino.clone();
-                            tetramino_cloned.rotate_clockwise();
-                            if tetramino_cloned.y + min_y <= 0 {
-                                return true;
-                            }
-                            if tetramino_cloned.y + max_y >= ROWS as i32 - 1 {
-                                return true;
-                            }
-                            if tetramino_cloned.x + min_x <= 0 {
-                                return true;
-                            }
-                            if tetramino_cloned.x + max_x >= COLS as i32 - 1 {
-                                return true;
-                            }
-                            let tile_x = x + tetramino_cloned.x;
-                            let tile_y = y + tetramino_cloned.y;
-                            for (_entity, tile) in field_query.iter() {
-                                if tile.x == tile_x && tile.y == tile_y && tile.value > 0 {
-                                    return true;
-                                }
-                            }
-                        },
-                        _ => {
-                            unreachable!();
-                        }
-                    }
-                }
-            }
+fn sync_ghost(game: Res<Game>, show_ghost: Res<ShowGhost>, mut query: Query<&mut GhostTetramino>) {
+    if !game.is_changed() && !show_ghost.is_changed() {
+        return;
+    }
+    let ghost = if show_ghost.0 && !game.is_over() && game.phase == Phase::Falling {
+        Some(game.ghost())
+    } else {
+        None
+    };
+    for mut shown in query.iter_mut() {
+        if shown.0 != ghost {
+            shown.0 = ghost;
         }
     }
-    return false;
 }
 
-fn on_collided( commands: &mut Commands,
-                tetramino_query: &Query<(Entity, &mut Tetramino)>,
-                field_query: &mut Query<(Entity, &mut Tile)>) {
-    for (entity, tetramino) in tetramino_query.iter() {
-        for (_tile_entity, mut tile) in field_query.iter_mut() {
-            let x = tile.x - tetramino.x;
-            if x < 0 || x >= 4 {
-                continue;
-            }
-            let y = tile.y - tetramino.y;
-            if y < 0 || y >= 4 {
-                continue;
-            }
-            if tetramino.shape[x as usize][y as usize] == 1 {
-                tile.value = match tetramino.tetramino_type {
-                    TetraminoType::I => 1,
-                    TetraminoType::S => 2,
-                    TetraminoType::Z => 3,
-                    TetraminoType::J => 4,
-                    TetraminoType::L => 5,
-                    TetraminoType::O => 6,
-                    TetraminoType::T => 7,
-                }
-            }
+fn on_ghost_changed(mut commands: Commands,
+                    layout: Res<BoardLayout>,
+                    mut query: Query<(Entity, &GhostTetramino, &mut Transform), Changed<GhostTetramino>>) {
+    for (entity, ghost, mut transform) in query.iter_mut() {
+        commands.entity(entity).despawn_descendants();
+        if let Some(tetramino) = &ghost.0 {
+            transform.translation = layout.get_coordinate(&tetramino.x, &tetramino.y) + Vec3::new(0.0, 0.0, 0.05);
+            let mut color = tile_color(tetramino.tetramino_type.value());
+            color.set_a(0.3);
+            commands.entity(entity).with_children(|parent| {
+                spawn_tetramino_sprites(parent, tetramino, layout.tile_size, |y| layout.cell_color(tetramino.y + y, color));
+            });
         }
-        create_random_tetramino(commands);
-        commands.entity(entity).despawn_recursive();
     }
 }
 
-fn fall_system(mut commands: Commands,
-               mut tetramino_query: Query<(Entity, &mut Tetramino)>,
-               mut field_query: Query<(Entity, &mut Tile)>) {
-    if check_is_collided(&tetramino_query, &field_query, (0, -1), 0) {
-        on_collided(&mut commands, &tetramino_query, &mut field_query);
-        return;
-    }
-    for (_entity, mut tetramino) in tetramino_query.iter_mut() {
-        tetramino.y -= 1;
+/// Spawns one `tile_size` sprite per filled cell of `tetramino`, relative to the bottom
+/// left corner of its 4x4 box. `color` gets the row of the cell in the box and leaves
+/// the cell out when it returns `None`.
+pub fn spawn_tetramino_sprites(parent: &mut ChildBuilder,
+                               tetramino: &Tetramino,
+                               tile_size: f32,
+                               color: impl Fn(i32) -> Option<Color>) {
+    for x in 0..4 {
+        for y in 0..4 {
+            if tetramino.shape[x][y] == 1 {
+                let color = match color(y as i32) {
+                    Some(color) => color,
+                    None => continue,
+                };
+                parent.spawn_bundle(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::new(tile_size, tile_size)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(Vec3::new(x as f32 * tile_size, y as f32 * tile_size, 0.0)),
+                    ..Default::default()
+                });
+            }
+        }
     }
 }
 
-#[cfg(test)]
-#[test]
-fn test_set_shape() {
-    let mut tetramino = Tetramino::new();
-    tetramino.set_shape(&TetraminoType::I);
-    assert_eq!(tetramino.shape, I_TETRAMINO);
-    tetramino.set_shape(&TetraminoType::S);
-    assert_eq!(tetramino.shape, S_TETRAMINO);
-    tetramino.set_shape(&TetraminoType::Z);
-    assert_eq!(tetramino.shape, Z_TETRAMINO);
-    tetramino.set_shape(&TetraminoType::J);
-    assert_eq!(tetramino.shape, J_TETRAMINO);
-    tetramino.set_shape(&TetraminoType::L);
-    assert_eq!(tetramino.shape, L_TETRAMINO);
-    tetramino.set_shape(&TetraminoType::O);
-    assert_eq!(tetramino.shape, O_TETRAMINO);
-    tetramino.set_shape(&TetraminoType::T);
-    assert_eq!(tetramino.shape, T_TETRAMINO);
-}
-
-#[cfg(test)]
-#[test]
-fn test_rotate_clockwise() {
-    let mut tetramino = Tetramino::new();
-    tetramino.set_shape(&TetraminoType::I);
-    tetramino.rotate_clockwise();
-    assert_eq!(tetramino.shape, [
-        [0, 0, 1, 0],
-        [0, 0, 1, 0],
-        [0, 0, 1, 0],
-        [0, 0, 1, 0],
-    ]);
-    tetramino.set_shape(&TetraminoType::S);
-    tetramino.rotate_clockwise();
-    assert_eq!(tetramino.shape, [
-        [0, 1, 0, 0],
-        [0, 1, 1, 0],
-        [0, 0, 1, 0],
-        [0, 0, 0, 0],
-    ]);
-}
-
-#[cfg(test)]
-#[test]
-fn test_rotate_conterclockwise() {
-    let mut tetramino = Tetramino::new();
-    tetramino.set_shape(&TetraminoType::I);
-    tetramino.rotate_conterclockwise();
-    assert_eq!(tetramino.shape, [
-        [0, 1, 0, 0],
-        [0, 1, 0, 0],
-        [0, 1, 0, 0],
-        [0, 1, 0, 0],
-    ]);
-    tetramino.set_shape(&TetraminoType::S);
-    tetramino.rotate_conterclockwise();
-    assert_eq!(tetramino.shape, [
-        [0, 0, 0, 0],
-        [0, 1, 0, 0],
-        [0, 1, 1, 0],
-        [0, 0, 1, 0],
-    ]);
+fn read_input(input: ActionInput, time: Res<Time>, mut game: ResMut<Game>) {
+    let input = PlayerInput {
+        left: input.pressed(Action::MoveLeft),
+        right: input.pressed(Action::MoveRight),
+        rotate_clockwise: input.pressed(Action::RotateCW),
+        rotate_conterclockwise: input.pressed(Action::RotateCCW),
+        rotate_180: input.pressed(Action::Rotate180),
+        hold: input.pressed(Action::Hold),
+        soft_drop: input.pressed(Action::SoftDrop),
+        hard_drop: input.pressed(Action::HardDrop),
+    };
+    game.step(&input, time.delta_seconds());
 }
-
-#[cfg(test)]
-#[test]
-fn test_create_tetramino() {
-    let mut tetramino = Tetramino::new();
-    tetramino.set_shape(&TetraminoType::I);
-    assert_eq!(tetramino.shape, I_TETRAMINO);
-    assert_eq!(tetramino.x, COLS as i32 / 2 - 2);
-    assert_eq!(tetramino.y, ROWS as i32 - 4);
-}
\ No newline at end of file
@@ -1,8 +1,89 @@
 use bevy::prelude::*;
+use tetris_rs::engine::{Board, Game, GameConfig, GameMode, Phase, ScoreEvent, GARBAGE};
+use crate::records::format_time;
+use crate::settings::Settings;
+use crate::state::{GameState, Playfield};
 
-pub const ROWS: usize = 20;
-pub const COLS: usize = 10;
-pub const TILE_SIZE: f32 = 20.0;
+/// Size of the board and of its cells, fixed at startup from `GameConfig` and `Settings`.
+/// Everything drawn in the world is laid out in cells through `get_coordinate`.
+pub struct BoardLayout {
+    pub cols: i32,
+    /// Visible rows, the hidden buffer starts right above them.
+    pub rows: i32,
+    /// World units per cell, which are pixels unless `fit_camera` has to zoom out.
+    pub tile_size: f32,
+}
+
+impl BoardLayout {
+    /// Cells taken on either side of the board by the hold box and the queue, with a
+    /// margin.
+    const SIDE_CELLS: i32 = 6;
+
+    /// Rows of the hidden buffer drawn above the board, faded, so that pieces can be seen
+    /// spawning. Anything higher is clipped.
+    pub const SHOWN_BUFFER_ROWS: i32 = 2;
+
+    /// Opacity of whatever is drawn in the shown buffer rows.
+    const BUFFER_ALPHA: f32 = 0.4;
+
+    pub fn new(board: &Board, tile_size: f32) -> Self {
+        Self {
+            cols: board.width(),
+            rows: board.visible_height(),
+            tile_size,
+        }
+    }
+
+    /// `color` as drawn in row `y` of the board: faded in the shown buffer rows, `None`
+    /// above them.
+    pub fn cell_color(&self, y: i32, mut color: Color) -> Option<Color> {
+        if y >= self.rows + Self::SHOWN_BUFFER_ROWS {
+            return None;
+        }
+        if y >= self.rows {
+            color.set_a(color.a() * Self::BUFFER_ALPHA);
+        }
+        Some(color)
+    }
+
+    /// Colour of the sprite of `tile`. Empty tiles of the buffer are not drawn at all.
+    fn tile_sprite_color(&self, tile: &Tile) -> Color {
+        if tile.value == 0 && tile.y >= self.rows {
+            return Color::NONE;
+        }
+        self.cell_color(tile.y, tile_color(tile.value)).unwrap_or(Color::NONE)
+    }
+
+    /// Centre of the cell `(x, y)` in world coordinates, the board being centred on the
+    /// origin.
+    pub fn get_coordinate(&self, x: &i32, y: &i32) -> Vec3 {
+        let mut res = Vec3::new(
+            *x as f32 * self.tile_size,
+            *y as f32 * self.tile_size,
+            0.0
+        );
+        res -= Vec3::new(
+            self.tile_size * self.cols as f32 / 2.0,
+            self.tile_size * self.rows as f32 / 2.0,
+            0.0
+        );
+        res
+    }
+
+    /// Camera scale that fits the board, the hold box and the queue in a window of
+    /// `width` by `height` pixels. Never zooms in past one pixel per world unit.
+    pub fn fit_scale(&self, width: f32, height: f32) -> f32 {
+        let needed = Vec2::new(
+            (self.cols + 2 * Self::SIDE_CELLS) as f32,
+            (self.rows + 2 * (Self::SHOWN_BUFFER_ROWS + 1)) as f32,
+        ) * self.tile_size;
+        (needed.x / width).max(needed.y / height).max(1.0)
+    }
+}
+
+/// The camera drawing the board, zoomed by `fit_camera`.
+#[derive(Component)]
+pub struct BoardCamera;
 
 #[derive(Component)]
 pub struct Tile {
@@ -14,26 +95,37 @@ pub struct Tile {
 #[derive(Component)]
 struct Score;
 
-#[derive(Default)]
-pub struct TetrisData {
-    pub score: i32,
-}
+/// Names the last scoring clear for a couple of seconds.
+#[derive(Component)]
+struct ClearText(Timer);
+
+/// How long `ClearText` stays up.
+const CLEAR_TEXT_SECONDS: f32 = 2.0;
 
 pub struct TilemapPlugin;
 
 impl Plugin for TilemapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(tiles_setup)
-            .init_resource::<TetrisData>()
-            .add_startup_system(create_score_text)
-            .add_system(on_tile_change)
-            .add_system(burn_the_line)
-            .add_system(update_score_text);
+        let config = app.world.get_resource::<GameConfig>().cloned().unwrap_or_default();
+        let settings = app.world.get_resource::<Settings>().cloned().unwrap_or_default();
+        let board = Board::with_size(config.width, config.height);
+        app.insert_resource(BoardLayout::new(&board, settings.tile_size))
+            .add_event::<ScoreEvent>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing)
+                .with_system(tiles_setup)
+                .with_system(create_score_text)
+                .with_system(create_clear_text))
+            .add_system(sync_tiles.after("game step"))
+            .add_system(on_tile_change.after(sync_tiles))
+            .add_system(flash_clearing_rows.after(on_tile_change))
+            .add_system(update_score_text.after("game step"))
+            .add_system(send_score_events.after("game step"))
+            .add_system(update_clear_text.after(send_score_events))
+            .add_system(fit_camera);
     }
 }
 
 fn create_score_text(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.spawn_bundle(UiCameraBundle::default());
     commands
     .spawn_bundle(TextBundle {
         text: Text {
@@ -50,128 +142,233 @@ fn create_score_text(mut commands: Commands, asset_server: Res<AssetServer>) {
         transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.2)),
         ..Default::default()
     }) 
-    .insert(Score);
+    .insert(Score)
+    .insert(Playfield);
 }
 
-fn update_score_text(mut commands: Commands, score: Res<TetrisData>, mut score_text: Query<(&mut Text, &Score)>) {
-    let score = score.score;
+fn update_score_text(game: Res<Game>, mut score_text: Query<(&mut Text, &Score)>) {
+    if !game.is_changed() {
+        return;
+    }
+    let data = game.data;
+    let lines = match game.line_goal() {
+        Some(goal) if game.config.mode == GameMode::Dig => format!("Garbage: {}/{}", data.garbage_lines, goal),
+        Some(goal) => format!("Lines: {}/{}", data.lines, goal),
+        None => format!("Lines: {}", data.lines),
+    };
+    let mut text = vec![format!("Score: {}", data.score), format!("Level: {}", data.level), lines];
+    match game.config.mode {
+        GameMode::Sprint | GameMode::Dig => {
+            text.push(format!("Time: {}", format_time(data.time)));
+            text.push(format!("PPS: {:.2}", data.pieces_per_second()));
+        }
+        GameMode::Ultra => text.push(format!("Time left: {}", format_time(game.time_left().unwrap_or_default()))),
+        GameMode::Endless | GameMode::Marathon => {}
+    }
+    text.push(format!("Seed: {}", game.seed));
     for (mut score_text, _) in score_text.iter_mut() {
-        score_text.sections[0].value = format!("Score: {}", score);
+        score_text.sections[0].value = text.join("\n");
+    }
+}
+
+fn create_clear_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+    .spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                bottom: Val::Px(20.0),
+                left: Val::Px(20.0),
+                ..default()
+            },
+            ..default()
+        },
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: asset_server.load("font.otf"),
+                font_size: 20.0,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    })
+    .insert(ClearText(Timer::from_seconds(CLEAR_TEXT_SECONDS, false)))
+    .insert(Playfield);
+}
+
+fn send_score_events(game: Res<Game>, mut events: EventWriter<ScoreEvent>) {
+    if game.is_changed() {
+        events.send_batch(game.score_events().iter().copied());
     }
 }
 
-pub fn get_coordinate(x: &i32, y: &i32) -> Vec3 {
-    let mut res = Vec3::new(
-        *x as f32 * TILE_SIZE, 
-        *y as f32 * TILE_SIZE,
-        0.0
-    );
-    res -= Vec3::new(
-        TILE_SIZE * COLS as f32 / 2.0, 
-        TILE_SIZE * ROWS as f32 / 2.0, 
-        0.0
-    );
-    res
+fn update_clear_text(time: Res<Time>, mut events: EventReader<ScoreEvent>, mut query: Query<(&mut Text, &mut ClearText)>) {
+    let last = events.iter().last();
+    for (mut text, mut clear_text) in query.iter_mut() {
+        if let Some(event) = last {
+            let mut lines = vec![event.clear.to_string()];
+            if event.back_to_back {
+                lines.push("Back-to-back".to_string());
+            }
+            if event.combo > 0 {
+                lines.push(format!("{} combo", event.combo));
+            }
+            if event.clear.perfect_clear {
+                lines.push("Perfect clear".to_string());
+            }
+            lines.push(format!("+{}", event.total));
+            text.sections[0].value = lines.join("\n");
+            clear_text.0.reset();
+        } else if clear_text.0.tick(time.delta()).just_finished() {
+            text.sections[0].value.clear();
+        }
+    }
 }
 
-pub fn tiles_setup(mut commands: Commands) {
-    for x in 0..COLS as i32 {
-        for y in 0..ROWS as i32 {
-            let tile_position = get_coordinate(&x, &y);
+pub fn tiles_setup(mut commands: Commands, layout: Res<BoardLayout>) {
+    for x in 0..layout.cols {
+        for y in 0..layout.rows + BoardLayout::SHOWN_BUFFER_ROWS {
+            let tile_position = layout.get_coordinate(&x, &y);
+            let tile = Tile { x, y, value: 0 };
 
             commands.spawn_bundle(SpriteBundle {
                 sprite: Sprite {
-                    color: Color::rgb(0.0, 0.0, 0.0),
-                    custom_size: Some(Vec2::new(TILE_SIZE, TILE_SIZE)),
+                    color: layout.tile_sprite_color(&tile),
+                    custom_size: Some(Vec2::new(layout.tile_size, layout.tile_size)),
                     ..default()
                 },
                 transform: Transform::from_translation(tile_position),
                 ..Default::default()
             })
-            .insert(Tile { x, y, value: 0 });
+            .insert(tile)
+            .insert(Playfield);
+        }
+    }
+}
+
+/// Zooms the camera out when the window is too small for the board at `tile_size`.
+fn fit_camera(
+    windows: Res<Windows>,
+    layout: Res<BoardLayout>,
+    mut query: Query<&mut OrthographicProjection, With<BoardCamera>>,
+) {
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let scale = layout.fit_scale(window.width(), window.height());
+    for mut projection in query.iter_mut() {
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
+    }
+}
+
+pub fn tile_color(value: u8) -> Color {
+    match value {
+        0 => Color::rgb(0.0, 0.0, 0.0), // nothing
+        1 => Color::rgb(0.0, 0.0, 1.0), // I
+        2 => Color::rgb(1.0, 0.0, 0.0), // S
+        3 => Color::rgb(0.0, 1.0, 0.0), // Z
+        4 => Color::rgb(0.0, 1.0, 1.0), // J
+        5 => Color::rgb(1.0, 1.0, 0.0), // L
+        6 => Color::rgb(1.0, 0.5, 0.0), // O
+        7 => Color::rgb(0.5, 0.0, 1.0), // T
+        GARBAGE => Color::rgb(0.5, 0.5, 0.5),
+        _ => unreachable!(),
+    }
+}
+
+/// Copies the locked cells of the board into the `Tile`s, touching only the tiles whose
+/// value actually differs so `on_tile_change` repaints just those. `Game` changes every
+/// frame the game runs, so the board last copied is kept around to skip the frames where
+/// only the piece moved.
+fn sync_tiles(
+    game: Res<Game>,
+    mut synced: Local<Option<Board>>,
+    added: Query<(), Added<Tile>>,
+    mut query: Query<&mut Tile>,
+) {
+    if added.is_empty() && synced.as_ref() == Some(&game.board) {
+        return;
+    }
+    *synced = Some(game.board.clone());
+    for mut tile in query.iter_mut() {
+        let value = game.board.get(tile.x, tile.y);
+        if tile.value != value {
+            tile.value = value;
         }
     }
 }
 
-fn on_tile_change(mut query: Query<(Entity, &Tile, &mut Sprite), Changed<Tile>>) {
+fn on_tile_change(layout: Res<BoardLayout>, mut query: Query<(Entity, &Tile, &mut Sprite), Changed<Tile>>) {
     for (_entity, tile, mut sprite) in query.iter_mut() {
-        sprite.as_mut().color = match tile.value {
-            0 => Color::rgb(0.0, 0.0, 0.0), // nothing
-            1 => Color::rgb(0.0, 0.0, 1.0), // I
-            2 => Color::rgb(1.0, 0.0, 0.0), // S
-            3 => Color::rgb(0.0, 1.0, 0.0), // Z
-            4 => Color::rgb(0.0, 1.0, 1.0), // J
-            5 => Color::rgb(1.0, 1.0, 0.0), // L
-            6 => Color::rgb(1.0, 0.5, 0.0), // O
-            7 => Color::rgb(0.5, 0.0, 1.0), // T
-            _ => unreachable!(),
-        };
-    }
-}
-
-fn burn_the_line(mut tetris_data: ResMut<TetrisData>,
-                 mut query: Query<&mut Tile>) {
-    let mut matrix = vec![vec![0; COLS]; ROWS + 1];
-    for tile in query.iter() {
-        matrix[tile.y as usize][tile.x as usize] = tile.value;
-    }
-    let mut burned = true;
-    let mut count = 0;
-    let mut line = 0;
-    while burned {
-        burned = false;
-        for y in 0..ROWS {
-            let mut line_is_full = true;
-            for x in 0..COLS {
-                if matrix[y][x] == 0 {
-                    line_is_full = false;
-                    break;
+        sprite.as_mut().color = layout.tile_sprite_color(tile);
+    }
+}
+
+/// Fades the rows being cleared from white to black over the line clear delay, then puts
+/// the colours of the collapsed stack back.
+fn flash_clearing_rows(game: Res<Game>,
+                       layout: Res<BoardLayout>,
+                       mut was_clearing: Local<bool>,
+                       mut query: Query<(&Tile, &mut Sprite)>) {
+    if !game.is_changed() {
+        return;
+    }
+    match &game.phase {
+        Phase::LineClear { rows, timer } => {
+            let brightness = 1.0 - (timer / game.config.line_clear_delay).min(1.0);
+            for (tile, mut sprite) in query.iter_mut() {
+                if rows.contains(&(tile.y as usize)) {
+                    let color = Color::rgb(brightness, brightness, brightness);
+                    sprite.color = layout.cell_color(tile.y, color).unwrap_or(Color::NONE);
                 }
             }
-            if line_is_full {
-                burned = true;
-                count += 1;
-                line = y;
-                break;
-            }
+            *was_clearing = true;
         }
-        if burned {
-            for y in line..ROWS {
-                for x in 0..COLS {
-                    matrix[y][x] = matrix[y + 1][x];
-                }
+        _ if *was_clearing => {
+            for (tile, mut sprite) in query.iter_mut() {
+                sprite.color = layout.tile_sprite_color(tile);
             }
+            *was_clearing = false;
         }
-    }
-    if count > 0 {
-        for mut tile in query.iter_mut() {
-            tile.value = matrix[tile.y as usize][tile.x as usize];
-        }
-        tetris_data.score += count * count * 100;
+        _ => {}
     }
 }
 
 #[cfg(test)]
 #[test]
 fn test_get_coordinate() {
-    assert_eq!(get_coordinate(&0, &0), Vec3::new(
-        -TILE_SIZE * COLS as f32 / 2.0, 
-        -TILE_SIZE * ROWS as f32 / 2.0, 
-        0.0
-    ));
-    assert_eq!(get_coordinate(&1, &0), Vec3::new(
-        -TILE_SIZE * COLS as f32 / 2.0 + TILE_SIZE, 
-        -TILE_SIZE * ROWS as f32 / 2.0, 
-        0.0
-    ));
-    assert_eq!(get_coordinate(&0, &1), Vec3::new(
-        -TILE_SIZE * COLS as f32 / 2.0, 
-        -TILE_SIZE * ROWS as f32 / 2.0 + TILE_SIZE, 
-        0.0
-    ));
-    assert_eq!(get_coordinate(&0, &10), Vec3::new(
-        -TILE_SIZE * COLS as f32 / 2.0, 
-        -TILE_SIZE * ROWS as f32 / 2.0 + TILE_SIZE * 10.0, 
-        0.0
-    ));
-}
\ No newline at end of file
+    let layout = BoardLayout { cols: 10, rows: 20, tile_size: 20.0 };
+    assert_eq!(layout.get_coordinate(&0, &0), Vec3::new(-100.0, -200.0, 0.0));
+    assert_eq!(layout.get_coordinate(&1, &0), Vec3::new(-80.0, -200.0, 0.0));
+    assert_eq!(layout.get_coordinate(&0, &1), Vec3::new(-100.0, -180.0, 0.0));
+    assert_eq!(layout.get_coordinate(&0, &10), Vec3::new(-100.0, 0.0, 0.0));
+    let wide = BoardLayout { cols: 16, rows: 20, tile_size: 10.0 };
+    assert_eq!(wide.get_coordinate(&8, &10), Vec3::ZERO);
+}
+
+#[cfg(test)]
+#[test]
+fn test_fit_scale() {
+    let layout = BoardLayout { cols: 10, rows: 20, tile_size: 20.0 };
+    // 22 by 26 cells of 20 pixels fit in 800 by 600.
+    assert_eq!(layout.fit_scale(800.0, 600.0), 1.0);
+    assert_eq!(layout.fit_scale(800.0, 260.0), 2.0);
+    let tall = BoardLayout { cols: 10, rows: 54, tile_size: 20.0 };
+    assert_eq!(tall.fit_scale(800.0, 600.0), 2.0);
+}
+
+#[cfg(test)]
+#[test]
+fn test_buffer_rows_fade_then_clip() {
+    let layout = BoardLayout { cols: 10, rows: 20, tile_size: 20.0 };
+    assert_eq!(layout.cell_color(19, Color::RED), Some(Color::RED));
+    assert_eq!(layout.cell_color(21, Color::RED).map(|color| color.a()), Some(BoardLayout::BUFFER_ALPHA));
+    assert_eq!(layout.cell_color(22, Color::RED), None);
+    assert_eq!(layout.tile_sprite_color(&Tile { x: 0, y: 20, value: 0 }), Color::NONE);
+    assert_eq!(layout.tile_sprite_color(&Tile { x: 0, y: 19, value: 0 }), tile_color(0));
+}